@@ -0,0 +1,25 @@
+//! Times `count_nqueens` for a few board sizes, to compare the speed of the
+//! solver across changes. Run with `cargo bench`.
+
+use std::{
+  hint::black_box,
+  time::{Duration, Instant},
+};
+
+use dlx::count_nqueens;
+
+/// Each size is counted this many times, and the fastest run is reported.
+const RUNS: usize = 5;
+
+fn main() {
+  for n in [8, 10, 12] {
+    let mut best = Duration::MAX;
+    let mut count = 0;
+    for _ in 0..RUNS {
+      let start = Instant::now();
+      count = count_nqueens(black_box(n));
+      best = best.min(start.elapsed());
+    }
+    println!("nqueens({n}): {count} solutions, best of {RUNS} runs: {best:?}");
+  }
+}
@@ -1,12 +1,16 @@
 use std::{
   borrow::BorrowMut,
   collections::{HashMap, HashSet},
-  fmt::{self, Debug, Formatter},
+  error::Error,
+  fmt::{self, Debug, Display, Formatter},
   hash::Hash,
   iter,
   marker::PhantomData,
+  sync::OnceLock,
 };
 
+use rand::{rngs::StdRng, SeedableRng};
+
 macro_rules! dlx_unreachable {
   ($msg:expr) => {
     if cfg!(debug_assertions) {
@@ -24,6 +28,36 @@ macro_rules! dlx_unreachable {
   };
 }
 
+mod checkpoint;
+mod chooser;
+mod dancing_cells;
+mod estimate;
+mod generate;
+mod givens;
+mod min_cost;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod preprocess;
+mod randomize;
+mod sample;
+mod search_options;
+mod solution;
+mod solver;
+mod stats;
+mod zdd;
+
+pub use checkpoint::*;
+pub use chooser::*;
+pub use dancing_cells::*;
+pub use estimate::*;
+pub use givens::*;
+pub use preprocess::*;
+pub use search_options::*;
+pub use solution::*;
+pub use solver::*;
+pub use stats::*;
+pub use zdd::*;
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct ColorItem<I> {
   item: I,
@@ -47,19 +81,26 @@ impl<I> ColorItem<I> {
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Constraint<I> {
   Primary(I),
+  /// A secondary item with a color, which may be shared with any other
+  /// subsets giving it the same color.
   Secondary(ColorItem<I>),
+  /// A secondary item without a color, which the subset uses exclusively: no
+  /// other chosen subset may use the item at all.
+  SecondaryUncolored(I),
 }
 
 impl<I> Constraint<I> {
   fn item(&self) -> &I {
     match self {
-      Constraint::Primary(item) | Constraint::Secondary(ColorItem { item, .. }) => item,
+      Constraint::Primary(item)
+      | Constraint::Secondary(ColorItem { item, .. })
+      | Constraint::SecondaryUncolored(item) => item,
     }
   }
 
   fn color(&self) -> Option<u32> {
     match self {
-      Constraint::Primary(_) => None,
+      Constraint::Primary(_) | Constraint::SecondaryUncolored(_) => None,
       Constraint::Secondary(ColorItem { color, .. }) => Some(*color),
     }
   }
@@ -77,6 +118,62 @@ impl<I> From<ColorItem<I>> for Constraint<I> {
   }
 }
 
+/// Reasons a set of items and subsets can't be turned into a `Dlx`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DlxError<I, N> {
+  /// The same item was listed more than once.
+  DuplicateItem(I),
+  /// Two subsets share the same name.
+  DuplicateSubsetName(N),
+  /// A subset references an item which was never declared.
+  UnknownItem { subset: N, item: I },
+  /// A subset uses a `Constraint::Primary` for a secondary item, or a
+  /// `Constraint::Secondary` for a primary item. `header_type` is the type the
+  /// item was declared with.
+  ConstraintTypeMismatch {
+    subset: N,
+    item: I,
+    header_type: HeaderType,
+  },
+  /// A `HeaderType::BoundedPrimary` item has `min > max` or `max == 0`.
+  InvalidBounds { item: I, min: u32, max: u32 },
+}
+
+impl<I, N> Display for DlxError<I, N>
+where
+  I: Debug,
+  N: Debug,
+{
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      DlxError::DuplicateItem(item) => write!(f, "Duplicate item {item:?}"),
+      DlxError::DuplicateSubsetName(name) => write!(f, "Duplicate subset name: {name:?}"),
+      DlxError::UnknownItem { subset, item } => {
+        write!(f, "Unknown item {item:?} in subset {subset:?}")
+      }
+      DlxError::ConstraintTypeMismatch {
+        subset,
+        item,
+        header_type,
+      } => write!(
+        f,
+        "Constraint on item {item:?} in subset {subset:?} does not match its item type ({header_type:?})"
+      ),
+      DlxError::InvalidBounds { item, min, max } => {
+        write!(f, "Invalid bounds {min}..={max} for item {item:?}")
+      }
+    }
+  }
+}
+
+impl<I, N> Error for DlxError<I, N>
+where
+  I: Debug,
+  N: Debug,
+{
+}
+
+#[derive(Clone)]
 struct ListNodeI<I> {
   prev: I,
   next: I,
@@ -86,22 +183,42 @@ type HeaderListNode = ListNodeI<u32>;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum HeaderType {
+  /// An item which must be covered exactly once.
   Primary,
+  /// A primary item which must be covered between `min` and `max` times
+  /// (inclusive). `Primary` is equivalent to `BoundedPrimary { min: 1, max: 1
+  /// }`.
+  BoundedPrimary { min: u32, max: u32 },
+  /// An item which may be covered at most once, or by any number of subsets
+  /// which all agree on its color.
   Secondary,
 }
 
+impl HeaderType {
+  fn is_primary(&self) -> bool {
+    match self {
+      HeaderType::Primary | HeaderType::BoundedPrimary { .. } => true,
+      HeaderType::Secondary => false,
+    }
+  }
+}
+
+#[derive(Clone)]
 struct Header<I> {
   item: Option<I>,
   node: HeaderListNode,
   header_type: HeaderType,
+  /// The number of additional times this item may be covered. Only
+  /// meaningful for primary items.
+  bound: u32,
+  /// The number of times this item may go uncovered out of its original
+  /// `bound`, i.e. `max - min`. Only meaningful for primary items.
+  slack: u32,
 }
 
 impl<I> Header<I> {
   fn is_primary(&self) -> bool {
-    match self.header_type {
-      HeaderType::Primary => true,
-      HeaderType::Secondary => false,
-    }
+    self.header_type.is_primary()
   }
 }
 
@@ -120,8 +237,11 @@ where
       self.node.prev,
       self.node.next,
       match self.header_type {
-        HeaderType::Primary => "Primary",
-        HeaderType::Secondary => "Secondary",
+        HeaderType::Primary => "Primary".to_string(),
+        HeaderType::BoundedPrimary { .. } => {
+          format!("Primary (bound: {}, slack: {})", self.bound, self.slack)
+        }
+        HeaderType::Secondary => "Secondary".to_string(),
       }
     )
   }
@@ -129,23 +249,32 @@ where
 
 type ListNode = ListNodeI<usize>;
 
+#[derive(Clone)]
 enum NodeType {
   Header {
     /// Number of constraints that have this item.
     size: usize,
   },
   Body {
-    /// The assigned color of this node, or None if this is a primary constraint.
+    /// The assigned color of this node, or None if this is a primary or
+    /// uncolored secondary constraint.
     color: Option<u32>,
+    /// True while the node's secondary item has been given the node's color
+    /// by a chosen subset, in which case the node is left in its item's list
+    /// when its subset is hidden.
+    purified: bool,
     /// The index of the header node associated with this node.
     top: u32,
   },
 }
 
+#[derive(Clone)]
 enum Node<N> {
   Boundary {
     /// The name of the subset listed to the left of this boundary.
     name: Option<N>,
+    /// The cost of the subset listed to the left of this boundary.
+    cost: u64,
     /// The index of the first node in the subset that comes before this
     /// boundary.
     first_for_prev: usize,
@@ -171,13 +300,23 @@ impl<I> Node<I> {
     }
   }
 
-  fn color_mut(&mut self) -> &mut Option<u32> {
+  fn is_purified(&self) -> bool {
     match self {
       Node::Normal {
-        node_type: NodeType::Body { color, .. },
+        node_type: NodeType::Body { purified, .. },
         ..
-      } => color,
-      _ => dlx_unreachable!("Unexpected color() called on non-body node"),
+      } => *purified,
+      _ => dlx_unreachable!("Unexpected is_purified() called on non-body node"),
+    }
+  }
+
+  fn purified_mut(&mut self) -> &mut bool {
+    match self {
+      Node::Normal {
+        node_type: NodeType::Body { purified, .. },
+        ..
+      } => purified,
+      _ => dlx_unreachable!("Unexpected purified_mut() called on non-body node"),
     }
   }
 
@@ -238,18 +377,24 @@ where
     match self {
       Node::Boundary {
         name,
+        cost,
         first_for_prev,
         last_for_next,
       } => {
         write!(
           f,
-          "{}: (first_prev: {}, last_next: {})",
+          "{}: (first_prev: {}, last_next: {}){}",
           match name {
             Some(name) => format!("{name:?}"),
             None => "[None]".to_string(),
           },
           first_for_prev,
-          last_for_next
+          last_for_next,
+          if *cost != 0 {
+            format!(" (cost: {cost})")
+          } else {
+            "".to_string()
+          }
         )
       }
       Node::Normal {
@@ -265,13 +410,18 @@ where
             NodeType::Header { size } => {
               format!("Header (size: {})", size)
             }
-            NodeType::Body { color, top } => {
+            NodeType::Body {
+              color,
+              purified,
+              top,
+            } => {
               format!(
-                "Body (top: {top}){}",
+                "Body (top: {top}){}{}",
                 match color {
                   Some(color) => format!(" (color: {color})"),
                   None => "".to_string(),
-                }
+                },
+                if *purified { " (purified)" } else { "" }
               )
             }
           }
@@ -291,10 +441,22 @@ enum ExploreNextChoiceResult {
   Done,
 }
 
+#[derive(Clone)]
 pub struct Dlx<I, N> {
   num_primary_items: usize,
   headers: Vec<Header<I>>,
   body: Vec<Node<N>>,
+  /// The number of links removed or restored by `hide` and `unhide`, for
+  /// `SearchStats`. Only counted while `count_updates` is set, so searches
+  /// without stats don't pay for it.
+  updates: u64,
+  count_updates: bool,
+  /// The first node of each subset by name, or None for subsets without any
+  /// items, built the first time `Dlx::with_givens` needs it.
+  subset_index: OnceLock<HashMap<N, Option<usize>>>,
+  /// The first node of each subset forced by `Dlx::with_givens`, in the
+  /// order they were forced.
+  forced: Vec<usize>,
 }
 
 impl<I, N> Dlx<I, N> {
@@ -344,21 +506,47 @@ impl<I, N> Dlx<I, N> {
     unsafe { self.body.get_unchecked_mut(idx) }
   }
 
-  fn to_top(&self, mut p: usize) -> usize {
-    p = self.body_node(p).next();
-    while let Node::Normal {
-      node_type,
-      item_node,
-    } = self.node(p)
+  /// Returns the boundary node which follows the subset containing the node
+  /// at `idx`.
+  fn subset_boundary(&self, idx: usize) -> &Node<N> {
+    ((idx + 1)..)
+      .map(|q| self.body_node(q))
+      .find(|node| matches!(node, Node::Boundary { .. }))
+      .unwrap()
+  }
+
+  /// Returns the cost of the subset containing the node at `idx`.
+  fn subset_cost(&self, idx: usize) -> u64 {
+    match self.subset_boundary(idx) {
+      Node::Boundary { cost, .. } => *cost,
+      Node::Normal { .. } => dlx_unreachable!(),
+    }
+  }
+
+  /// Returns the index of the header of the body node at `idx`.
+  fn top_for_node(&self, idx: usize) -> usize {
+    if let Node::Normal {
+      node_type: NodeType::Body { top, .. },
+      ..
+    } = self.body_node(idx)
     {
-      match node_type {
-        NodeType::Header { .. } => return p,
-        NodeType::Body { .. } => {
-          p = item_node.next;
-        }
-      }
+      *top as usize
+    } else {
+      dlx_unreachable!("Unexpected top_for_node() called on non-body node")
+    }
+  }
+
+  /// Returns the item of the body node at `idx`.
+  fn item_for_node(&self, idx: usize) -> &I {
+    self.header(self.top_for_node(idx)).item.as_ref().unwrap()
+  }
+
+  /// Returns the name of the subset containing the node at `idx`.
+  fn subset_name(&self, idx: usize) -> &N {
+    match self.subset_boundary(idx) {
+      Node::Boundary { name, .. } => name.as_ref().unwrap(),
+      Node::Normal { .. } => dlx_unreachable!(),
     }
-    dlx_unreachable!("Unexpected boundary node found in queue: {p}");
   }
 
   fn iterate_items(&self, idx: usize) -> impl Iterator<Item = usize> + '_ {
@@ -403,15 +591,18 @@ impl<I, N> Dlx<I, N> {
         }
         Node::Normal {
           item_node,
-          node_type: NodeType::Body { top, color },
+          node_type: NodeType::Body { top, purified, .. },
         } => {
           let top = *top as usize;
 
-          if self.header(top).is_primary() || color.is_some() {
+          if !purified {
             let prev_idx = item_node.prev;
             let next_idx = item_node.next;
             self.node_mut(prev_idx).set_next(next_idx);
             self.node_mut(next_idx).set_prev(prev_idx);
+            if self.count_updates {
+              self.updates = self.updates.wrapping_add(1);
+            }
           }
           let len_mut = self.body_header_mut(top).len_mut();
           *len_mut = len_mut.wrapping_sub(1);
@@ -436,15 +627,18 @@ impl<I, N> Dlx<I, N> {
         }
         Node::Normal {
           item_node,
-          node_type: NodeType::Body { top, color },
+          node_type: NodeType::Body { top, purified, .. },
         } => {
           let top = *top as usize;
 
-          if self.header(top).is_primary() || color.is_some() {
+          if !purified {
             let prev_idx = item_node.prev;
             let next_idx = item_node.next;
             self.node_mut(prev_idx).set_next(q);
             self.node_mut(next_idx).set_prev(q);
+            if self.count_updates {
+              self.updates = self.updates.wrapping_add(1);
+            }
           }
           let len_mut = self.body_header_mut(top).len_mut();
           *len_mut = len_mut.wrapping_add(1);
@@ -462,7 +656,7 @@ impl<I, N> Dlx<I, N> {
   /// from the items list.
   fn cover(&mut self, idx: usize) {
     // println!("Covering {:?}", self.header(idx).item.as_ref().unwrap());
-    debug_assert!((1..=self.num_primary_items).contains(&idx));
+    debug_assert!((1..(self.headers.len() - 1)).contains(&idx));
     let mut p = self.body_header(idx).next();
     while p != idx {
       self.hide(p);
@@ -470,6 +664,27 @@ impl<I, N> Dlx<I, N> {
     }
 
     // Hide this item in the items list.
+    self.unlink_item(idx);
+  }
+
+  /// Reverts `cover(idx)`, assuming the state of Dlx was exactly as it was
+  /// when `cover(idx)` was called.
+  fn uncover(&mut self, idx: usize) {
+    debug_assert!((1..(self.headers.len() - 1)).contains(&idx));
+    // Put this item back in the items list.
+    self.relink_item(idx);
+
+    let mut p = self.body_header(idx).prev();
+    while p != idx {
+      self.unhide(p);
+      p = self.body_node(p).prev();
+    }
+    // println!("Uncovering {:?}", self.header(idx).item.as_ref().unwrap());
+  }
+
+  /// Removes the header `idx` from the items list, without touching any of
+  /// the subsets containing it.
+  fn unlink_item(&mut self, idx: usize) {
     let header = self.header(idx);
     let prev_idx = header.node.prev;
     let next_idx = header.node.next;
@@ -477,23 +692,87 @@ impl<I, N> Dlx<I, N> {
     self.header_mut(next_idx as usize).node.prev = prev_idx;
   }
 
-  /// Reverts `cover(idx)`, assuming the state of Dlx was exactly as it was
-  /// when `cover(idx)` was called.
-  fn uncover(&mut self, idx: usize) {
-    debug_assert!((1..=self.num_primary_items).contains(&idx));
-    // Put this item back in the items list.
+  /// Reverts `unlink_item(idx)`.
+  fn relink_item(&mut self, idx: usize) {
     let header = self.header(idx);
     let prev_idx = header.node.prev;
     let next_idx = header.node.next;
     self.header_mut(prev_idx as usize).node.next = idx as u32;
     self.header_mut(next_idx as usize).node.prev = idx as u32;
+  }
 
-    let mut p = self.body_header(idx).prev();
-    while p != idx {
-      self.unhide(p);
-      p = self.body_node(p).prev();
+  /// Removes the subset containing the node at `idx` from the list of the
+  /// primary item `top` after it has been tried as a choice for `top`, so it
+  /// can't be chosen again deeper in the search. If `top` hasn't been covered,
+  /// the subset is also hidden from all other items.
+  fn tweak(&mut self, idx: usize, top: usize) {
+    if self.header(top).bound != 0 {
+      self.hide(idx);
     }
-    // println!("Uncovering {:?}", self.header(idx).item.as_ref().unwrap());
+    let node = self.body_node(idx);
+    let prev_idx = node.prev();
+    let next_idx = node.next();
+    self.node_mut(prev_idx).set_next(next_idx);
+    self.node_mut(next_idx).set_prev(prev_idx);
+    let len_mut = self.body_header_mut(top).len_mut();
+    *len_mut = len_mut.wrapping_sub(1);
+  }
+
+  /// Reverts every `tweak` made to the item `top` since the node at `first`
+  /// was tweaked, which must have been at the front of the list for `top`.
+  fn untweak(&mut self, first: usize, top: usize) {
+    let unhide = self.header(top).bound != 0;
+    let last = self.body_header(top).next();
+    self.body_header_mut(top).set_next(first);
+
+    let mut prev = top;
+    let mut p = first;
+    let mut count = 0;
+    while p != last {
+      self.body_node_mut(p).set_prev(prev);
+      if unhide {
+        self.unhide(p);
+      }
+      count += 1;
+      prev = p;
+      p = self.body_node(p).next();
+    }
+    self.node_mut(last).set_prev(prev);
+
+    let len_mut = self.body_header_mut(top).len_mut();
+    *len_mut = len_mut.wrapping_add(count);
+  }
+
+  /// Prepares to branch on the primary item `idx`, taking one from its bound
+  /// and covering it if that was its last allowed use. Returns the first
+  /// subset to try for the item, or `idx` itself if there are none.
+  fn enter_item(&mut self, idx: usize) -> usize {
+    let header = self.header_mut(idx);
+    header.bound -= 1;
+    if header.bound == 0 {
+      self.cover(idx);
+    }
+    self.body_header(idx).next()
+  }
+
+  /// Reverts `enter_item(idx)` along with all the tweaks made while
+  /// branching on `idx`, starting from `first_tweak`. If `unlinked`, the item
+  /// was removed from the items list after all of its subsets were tried.
+  fn leave_item(&mut self, idx: usize, first_tweak: usize, unlinked: bool) {
+    let header = self.header(idx);
+    let bound = header.bound;
+    if bound == 0 && header.slack == 0 {
+      self.uncover(idx);
+    } else {
+      if unlinked && bound != 0 {
+        self.relink_item(idx);
+      }
+      self.untweak(first_tweak, idx);
+      if bound == 0 {
+        self.uncover(idx);
+      }
+    }
+    self.header_mut(idx).bound += 1;
   }
 
   /// Covers all subsets with secondary constraints which don't have the same
@@ -501,10 +780,12 @@ impl<I, N> Dlx<I, N> {
   fn purify(&mut self, idx: usize) {
     let (color, top) = match self.body_node(idx) {
       Node::Normal {
-        node_type: NodeType::Body {
-          color: Some(color),
-          top,
-        },
+        node_type:
+          NodeType::Body {
+            color: Some(color),
+            top,
+            ..
+          },
         ..
       } => (*color, *top as usize),
       _ => dlx_unreachable!("Unexpected uncolored node for secondary constraint at index {idx}."),
@@ -517,10 +798,9 @@ impl<I, N> Dlx<I, N> {
 
     let mut p = self.body_header(top).next();
     while p != top {
-      let p_color = self.body_node_mut(p).color_mut();
-      // println!("Looking at {p} ({p_color:?})");
-      if *p_color == Some(color) {
-        *p_color = None;
+      // println!("Looking at {p} ({:?})", self.body_node(p).color());
+      if self.body_node(p).color() == Some(color) {
+        *self.body_node_mut(p).purified_mut() = true;
       } else {
         self.hide(p);
       }
@@ -533,10 +813,12 @@ impl<I, N> Dlx<I, N> {
   fn unpurify(&mut self, idx: usize) {
     let (color, top) = match self.body_node(idx) {
       Node::Normal {
-        node_type: NodeType::Body {
-          color: Some(color),
-          top,
-        },
+        node_type:
+          NodeType::Body {
+            color: Some(color),
+            top,
+            ..
+          },
         ..
       } => (*color, *top as usize),
       _ => dlx_unreachable!("Unexpected uncolored node for secondary constraint at index {idx}."),
@@ -544,9 +826,8 @@ impl<I, N> Dlx<I, N> {
 
     let mut p = self.body_header(top).prev();
     while p != top {
-      let p_color = self.body_node_mut(p).color_mut();
-      if p_color.is_none() {
-        *p_color = Some(color);
+      if self.body_node(p).color() == Some(color) {
+        *self.body_node_mut(p).purified_mut() = false;
       } else {
         self.unhide(p);
       }
@@ -561,17 +842,31 @@ impl<I, N> Dlx<I, N> {
   fn commit(&mut self, idx: usize, top: usize) {
     // println!("Committing {idx} (top: {top})");
     if self.header(top).is_primary() {
-      self.cover(top);
-    } else if self.body_node(idx).color().is_some() {
-      self.purify(idx);
+      let header = self.header_mut(top);
+      header.bound -= 1;
+      if header.bound == 0 {
+        self.cover(top);
+      }
+    } else if !self.body_node(idx).is_purified() {
+      // Purified nodes were already given their color by an earlier choice.
+      match self.body_node(idx).color() {
+        Some(_) => self.purify(idx),
+        None => self.cover(top),
+      }
     }
   }
 
   fn uncommit(&mut self, idx: usize, top: usize) {
     if self.header(top).is_primary() {
-      self.uncover(top);
-    } else if self.body_node(idx).color().is_some() {
-      self.unpurify(idx);
+      if self.header(top).bound == 0 {
+        self.uncover(top);
+      }
+      self.header_mut(top).bound += 1;
+    } else if !self.body_node(idx).is_purified() {
+      match self.body_node(idx).color() {
+        Some(_) => self.unpurify(idx),
+        None => self.uncover(top),
+      }
     }
     // println!("Uncommitting {idx} (top: {top})");
   }
@@ -632,14 +927,26 @@ impl<I, N> Dlx<I, N> {
     // println!("Uncovering remaining for {idx}");
   }
 
+  /// Returns the number of ways the search can branch on the primary item
+  /// `idx`. See `choose_item`.
+  fn branching_factor(&self, idx: usize) -> usize {
+    let header = self.header(idx);
+    let required = header.bound.saturating_sub(header.slack) as usize;
+    (self.body_header(idx).len() + 1).saturating_sub(required)
+  }
+
   /// Chooses the index of the next item to try covering, using the LRV
   /// heuristic (least remaining values). Returns None if there are no items
   /// left, meaning a solution has been found.
+  ///
+  /// For items with multiplicities, the number of remaining values is the
+  /// number of ways to branch on the item: one per subset, plus one for
+  /// covering it no more times, less the number of subsets still required.
   fn choose_item(&self) -> Option<u32> {
     let mut opt = self.header(0).node.next;
     let mut best_opt = (None, 0);
     while opt != 0 {
-      let len = self.body_header(opt as usize).len();
+      let len = self.branching_factor(opt as usize);
       best_opt = match best_opt {
         (Some(_), min_len) => {
           if min_len > len {
@@ -676,6 +983,60 @@ impl<I, N> Dlx<I, N> {
   ) -> impl DlxIterator<I, N, StepwiseDlxIterResult<Vec<usize>>> {
     StepwiseDlxIteratorImpl::new(self)
   }
+
+  /// Like `find_solutions`, but searching with `options`. If the search is
+  /// stopped by one of the limits in `options`, the iterator ends early, and
+  /// `DlxIterator::status` reports which limit was hit.
+  pub fn find_solutions_with(&mut self, options: SearchOptions<I>) -> impl DlxIterator<I, N> + '_ {
+    DlxIteratorImpl::with_options(self, options)
+  }
+
+  pub fn into_solutions_with(self, options: SearchOptions<I>) -> impl DlxIterator<I, N> {
+    DlxIteratorImpl::with_options(self, options)
+  }
+
+  pub fn find_solutions_stepwise_with(
+    &mut self,
+    options: SearchOptions<I>,
+  ) -> impl DlxIterator<I, N, StepwiseDlxIterResult<Vec<usize>>> + '_ {
+    StepwiseDlxIteratorImpl::with_options(self, options)
+  }
+
+  pub fn into_solutions_stepwise_with(
+    self,
+    options: SearchOptions<I>,
+  ) -> impl DlxIterator<I, N, StepwiseDlxIterResult<Vec<usize>>> {
+    StepwiseDlxIteratorImpl::with_options(self, options)
+  }
+
+  /// Iterates over the items of this problem, with all primary items before
+  /// all secondary items, and otherwise in the order they were given in.
+  pub fn items(&self) -> impl Iterator<Item = (&I, HeaderType)> + '_ {
+    self.headers[1..(self.headers.len() - 1)]
+      .iter()
+      .map(|header| (header.item.as_ref().unwrap(), header.header_type))
+  }
+
+  /// Counts the solutions, without materializing any of them.
+  pub fn count_solutions(&mut self) -> u64 {
+    self.count_solutions_up_to(u64::MAX)
+  }
+
+  /// Counts the solutions, stopping the search once `limit` have been found.
+  /// For example, `count_solutions_up_to(2) == 1` checks that there is
+  /// exactly one solution.
+  pub fn count_solutions_up_to(&mut self, limit: u64) -> u64 {
+    let mut explorer = DlxExplorer::new(self);
+    let mut count = 0;
+    while count < limit {
+      match explorer.step() {
+        DlxStepResult::Continue => {}
+        DlxStepResult::FoundSolution(_) => count += 1,
+        DlxStepResult::Done => break,
+      }
+    }
+    count
+  }
 }
 
 impl<I, N> Dlx<I, N>
@@ -683,31 +1044,35 @@ where
   I: Clone,
 {
   fn item_name(&self, idx: usize) -> I {
-    debug_assert!(matches!(
-      self.body_node(idx),
-      Node::Normal {
-        node_type: NodeType::Body { .. },
-        ..
-      }
-    ));
-    if let Node::Normal {
-      node_type: NodeType::Body { top, .. },
-      ..
-    } = self.body_node(idx)
-    {
-      self.header(*top as usize).item.clone().unwrap()
-    } else {
-      dlx_unreachable!()
+    self.item_for_node(idx).clone()
+  }
+
+  fn constraint_for_node(&self, idx: usize) -> Constraint<I> {
+    match self.body_node(idx).color() {
+      Some(color) => ColorItem::new(self.item_name(idx), color).into(),
+      None if self.header(self.top_for_node(idx)).is_primary() => self.item_name(idx).into(),
+      None => Constraint::SecondaryUncolored(self.item_name(idx)),
     }
   }
 
   fn items_for_node(&self, idx: usize) -> impl Iterator<Item = Constraint<I>> + '_ {
     self
       .iterate_items(idx)
-      .map(move |item_idx| match self.body_node(item_idx).color() {
-        Some(color) => ColorItem::new(self.item_name(item_idx), color).into(),
-        None => self.item_name(item_idx).into(),
-      })
+      .map(move |item_idx| self.constraint_for_node(item_idx))
+  }
+
+  /// Iterates over the subsets of this problem in the order they are tried,
+  /// as `(name, constraints)` pairs.
+  pub fn subsets(&self) -> impl Iterator<Item = (&N, Vec<Constraint<I>>)> + '_ {
+    let mut start = self.headers.len();
+    (self.headers.len()..self.body.len()).filter_map(move |idx| match self.body_node(idx) {
+      Node::Boundary { name, .. } => {
+        let constraints = (start..idx).map(|q| self.constraint_for_node(q)).collect();
+        start = idx + 1;
+        Some((name.as_ref().unwrap(), constraints))
+      }
+      Node::Normal { .. } => None,
+    })
   }
 }
 
@@ -716,12 +1081,26 @@ where
   N: Clone,
 {
   fn set_name_for_node(&self, idx: usize) -> N {
-    ((idx + 1)..)
-      .find_map(|q| match self.body_node(q) {
-        Node::Boundary { name, .. } => Some(name.clone().unwrap()),
-        Node::Normal { .. } => None,
+    self.subset_name(idx).clone()
+  }
+
+  /// Maps a solution, as a list of node indices, to the names of the subsets
+  /// chosen in it.
+  pub fn solution_names(&self, solution: &[usize]) -> Vec<N> {
+    solution
+      .iter()
+      .filter_map(|&p| {
+        if let Node::Normal {
+          node_type: NodeType::Body { .. },
+          ..
+        } = self.node(p)
+        {
+          Some(self.set_name_for_node(p))
+        } else {
+          None
+        }
       })
-      .unwrap()
+      .collect()
   }
 }
 
@@ -730,6 +1109,15 @@ where
   I: Hash + Eq + Clone + Debug,
   N: Hash + Eq + Clone + Debug,
 {
+  /// Builds a problem from its items and its subsets, given as `(name,
+  /// constraints)` pairs. Subsets are tried in the order they are given in,
+  /// which is the order solutions are found in. Panics if the items or
+  /// subsets are malformed (see `Dlx::try_new`).
+  ///
+  /// Subsets built this way all cost 0. To give them costs for
+  /// `Dlx::find_min_cost_solutions`, use `Dlx::new_with_costs`, but note that
+  /// it reorders the subsets by cost, which changes the order solutions are
+  /// found in.
   pub fn new<U, S, C, D>(items: U, subsets: S) -> Self
   where
     U: IntoIterator<Item = (I, HeaderType)>,
@@ -737,20 +1125,72 @@ where
     C: IntoIterator<Item = D>,
     D: Into<Constraint<I>>,
   {
-    Self::construct(items, subsets)
+    Self::try_new(items, subsets).unwrap_or_else(|err| panic!("{err}"))
   }
 
-  fn construct<U, S, C, D>(items: U, subsets: S) -> Self
+  /// Like `Dlx::new`, but returns an error instead of panicking if the items
+  /// or subsets are malformed.
+  pub fn try_new<U, S, C, D>(items: U, subsets: S) -> Result<Self, DlxError<I, N>>
   where
     U: IntoIterator<Item = (I, HeaderType)>,
     S: IntoIterator<Item = (N, C)>,
     C: IntoIterator<Item = D>,
     D: Into<Constraint<I>>,
+  {
+    Self::construct(
+      items,
+      subsets
+        .into_iter()
+        .map(|(name, constraints)| (name, 0, constraints)),
+    )
+  }
+
+  /// Like `Dlx::new`, but each subset is given as `(name, cost, constraints)`
+  /// for use with `Dlx::find_min_cost_solutions`.
+  ///
+  /// Unlike `Dlx::new`, this reorders the subsets: they are tried in order of
+  /// increasing cost, with ties broken by the order they are given in, so
+  /// `Dlx::find_solutions` and `Dlx::subsets` list them in that order rather
+  /// than the order given.
+  pub fn new_with_costs<U, S, C, D>(items: U, subsets: S) -> Self
+  where
+    U: IntoIterator<Item = (I, HeaderType)>,
+    S: IntoIterator<Item = (N, u64, C)>,
+    C: IntoIterator<Item = D>,
+    D: Into<Constraint<I>>,
+  {
+    Self::try_new_with_costs(items, subsets).unwrap_or_else(|err| panic!("{err}"))
+  }
+
+  /// Like `Dlx::new_with_costs`, but returns an error instead of panicking if
+  /// the items or subsets are malformed.
+  pub fn try_new_with_costs<U, S, C, D>(items: U, subsets: S) -> Result<Self, DlxError<I, N>>
+  where
+    U: IntoIterator<Item = (I, HeaderType)>,
+    S: IntoIterator<Item = (N, u64, C)>,
+    C: IntoIterator<Item = D>,
+    D: Into<Constraint<I>>,
+  {
+    // Sorting the subsets by cost keeps every item's list sorted by cost, so
+    // the cheapest remaining subset for an item is always at its front.
+    let mut subsets = subsets.into_iter().collect::<Vec<_>>();
+    subsets.sort_by_key(|(_, cost, _)| *cost);
+    Self::construct(items, subsets)
+  }
+
+  fn construct<U, S, C, D>(items: U, subsets: S) -> Result<Self, DlxError<I, N>>
+  where
+    U: IntoIterator<Item = (I, HeaderType)>,
+    S: IntoIterator<Item = (N, u64, C)>,
+    C: IntoIterator<Item = D>,
+    D: Into<Constraint<I>>,
   {
     let mut headers = vec![Header {
       item: None,
       node: ListNodeI { prev: 0, next: 1 },
       header_type: HeaderType::Primary,
+      bound: 0,
+      slack: 0,
     }];
     let mut item_map = HashMap::new();
     let mut body = Vec::new();
@@ -760,47 +1200,54 @@ where
     // Push phony node to first element of body.
     body.push(Node::Boundary {
       name: None,
+      cost: 0,
       first_for_prev: 0,
       last_for_next: 0,
     });
 
-    let (primary_headers, secondary_headers): (Vec<_>, Vec<_>) =
-      items
-        .into_iter()
-        .partition(|(_, header_type)| match header_type {
-          HeaderType::Primary => true,
-          HeaderType::Secondary => false,
-        });
+    let (primary_headers, secondary_headers): (Vec<_>, Vec<_>) = items
+      .into_iter()
+      .partition(|(_, header_type)| header_type.is_primary());
 
     let primary_headers_len = primary_headers.len() as u32;
-    headers.extend(
-      primary_headers
-        .into_iter()
-        .chain(secondary_headers)
-        .enumerate()
-        .map(|(idx, (item, header_type))| {
-          let new_idx = idx + 1;
-          if item_map.insert(item.clone(), new_idx).is_some() {
-            panic!("Duplicate item {:?}", item);
+    for (idx, (item, header_type)) in primary_headers
+      .into_iter()
+      .chain(secondary_headers)
+      .enumerate()
+    {
+      let new_idx = idx + 1;
+      let (bound, slack) = match header_type {
+        HeaderType::Primary => (1, 0),
+        HeaderType::BoundedPrimary { min, max } => {
+          if min > max || max == 0 {
+            return Err(DlxError::InvalidBounds { item, min, max });
           }
-          body.push(Node::Normal {
-            item_node: ListNodeI {
-              prev: new_idx,
-              next: new_idx,
-            },
-            node_type: NodeType::Header { size: 0 },
-          });
+          (max, max - min)
+        }
+        HeaderType::Secondary => (0, 0),
+      };
+      if item_map.insert(item.clone(), new_idx).is_some() {
+        return Err(DlxError::DuplicateItem(item));
+      }
+      body.push(Node::Normal {
+        item_node: ListNodeI {
+          prev: new_idx,
+          next: new_idx,
+        },
+        node_type: NodeType::Header { size: 0 },
+      });
 
-          Header {
-            item: Some(item),
-            node: ListNodeI {
-              prev: new_idx as u32 - 1,
-              next: new_idx as u32 + 1,
-            },
-            header_type,
-          }
-        }),
-    );
+      headers.push(Header {
+        item: Some(item),
+        node: ListNodeI {
+          prev: new_idx as u32 - 1,
+          next: new_idx as u32 + 1,
+        },
+        header_type,
+        bound,
+        slack,
+      });
+    }
     let last_idx = headers.len();
     headers.push(Header {
       item: None,
@@ -809,6 +1256,8 @@ where
         next: primary_headers_len + 1,
       },
       header_type: HeaderType::Secondary,
+      bound: 0,
+      slack: 0,
     });
     headers.get_mut(0).unwrap().node.prev = primary_headers_len;
     headers
@@ -825,46 +1274,39 @@ where
 
     body.push(Node::Boundary {
       name: None,
+      cost: 0,
       first_for_prev: 0,
       last_for_next: 0,
     });
 
-    for (name, constraints) in subsets {
+    for (name, cost, constraints) in subsets {
       if !subset_names.insert(name.clone()) {
-        panic!("Duplicate subset name: {name:?}");
+        return Err(DlxError::DuplicateSubsetName(name));
       }
 
       last_start_index = body.len();
-      constraints.into_iter().for_each(|constraint| {
+      for constraint in constraints {
         let constraint: Constraint<I> = constraint.into();
         let idx = body.len();
 
-        let header_idx = *item_map
-          .get(constraint.item())
-          .unwrap_or_else(|| panic!("Unknown item {:?}", constraint.item()));
-        let header = body.get_mut(header_idx).unwrap();
-        let prev_idx = header.prev();
+        let Some(&header_idx) = item_map.get(constraint.item()) else {
+          return Err(DlxError::UnknownItem {
+            subset: name,
+            item: constraint.item().clone(),
+          });
+        };
 
-        debug_assert!(
-          matches!(
-            (headers.get(header_idx).unwrap(), &constraint),
-            (
-              Header {
-                header_type: HeaderType::Primary,
-                ..
-              },
-              Constraint::Primary(_),
-            ) | (
-              Header {
-                header_type: HeaderType::Secondary,
-                ..
-              },
-              Constraint::Secondary(_),
-            )
-          ),
-          "Expect constraint type to match item type (primary vs. secondary)"
-        );
+        let header_type = headers.get(header_idx).unwrap().header_type;
+        if header_type.is_primary() != matches!(constraint, Constraint::Primary(_)) {
+          return Err(DlxError::ConstraintTypeMismatch {
+            subset: name,
+            item: constraint.item().clone(),
+            header_type,
+          });
+        }
 
+        let header = body.get_mut(header_idx).unwrap();
+        let prev_idx = header.prev();
         header.set_prev(idx);
         *header.len_mut() += 1;
         body.get_mut(prev_idx).unwrap().set_next(idx);
@@ -876,10 +1318,11 @@ where
           },
           node_type: NodeType::Body {
             color: constraint.color(),
+            purified: false,
             top: header_idx as u32,
           },
         });
-      });
+      }
 
       let last_idx = body.len() - 1;
       if let Some(Node::Boundary { last_for_next, .. }) = body.get_mut(last_start_index - 1) {
@@ -890,17 +1333,22 @@ where
 
       body.push(Node::Boundary {
         name: Some(name),
+        cost,
         first_for_prev: last_start_index,
         last_for_next: 0,
       });
     }
 
     let num_primary_items = headers.first().unwrap().node.prev as usize;
-    Dlx {
+    Ok(Dlx {
       headers,
       body,
       num_primary_items,
-    }
+      updates: 0,
+      count_updates: false,
+      subset_index: OnceLock::new(),
+      forced: Vec::new(),
+    })
   }
 }
 
@@ -932,14 +1380,46 @@ enum DlxExplorerState {
   NotStarted,
 }
 
+/// Bookkeeping for one level of the search, kept alongside the entry for the
+/// level in `partial_solution`.
+#[derive(Clone, Copy, Debug)]
+struct SearchLevel {
+  /// The first subset of the item chosen at this level, from which subsets
+  /// are tweaked out of the item's list as they are tried.
+  first_tweak: usize,
+  /// True once every subset of the item has been tried, and the search has
+  /// moved on without covering the item any more times.
+  exhausted: bool,
+}
+
 #[derive(Debug)]
 struct DlxExplorer<D, I, N>
 where
   D: BorrowMut<Dlx<I, N>>,
 {
   dlx: D,
+  /// For each level of the search, either the node of the subset chosen at
+  /// that level, or the header of the item being branched on if no subset
+  /// has been chosen for it.
   partial_solution: Vec<usize>,
+  levels: Vec<SearchLevel>,
+  /// The number of levels at the bottom of `partial_solution` which the
+  /// search may not backtrack past.
+  floor: usize,
   state: DlxExplorerState,
+  options: SearchOptions<I>,
+  /// The number of nodes of the search tree visited by `step`.
+  nodes: u64,
+  status: SearchStatus,
+  stats: Option<SearchStats>,
+  /// The value of `Dlx::updates` when the search started.
+  initial_updates: u64,
+  /// The value of `Dlx::count_updates` before the search started, which is
+  /// put back when it ends.
+  prev_count_updates: bool,
+  /// True if the item lists were shuffled for a randomized search, and need
+  /// to be put back in order when the search is done.
+  shuffled: bool,
   _phantom: PhantomData<(I, N)>,
 }
 
@@ -948,14 +1428,52 @@ where
   D: BorrowMut<Dlx<I, N>>,
 {
   fn new(dlx: D) -> Self {
+    Self::with_options(dlx, SearchOptions::default())
+  }
+
+  fn with_options(mut dlx: D, mut options: SearchOptions<I>) -> Self {
+    let initial_updates = dlx.borrow().updates;
+    let prev_count_updates = dlx.borrow().count_updates;
+    if options.collect_stats {
+      dlx.borrow_mut().count_updates = true;
+    }
+    let stats = options.collect_stats.then(SearchStats::default);
+    let shuffled = if let Some(seed) = options.seed {
+      let mut rng = StdRng::seed_from_u64(seed);
+      dlx.borrow_mut().shuffle_item_lists(&mut rng);
+      options
+        .chooser
+        .get_or_insert_with(|| Box::new(RandomizedMrv::new(StdRng::from_rng(&mut rng))));
+      true
+    } else {
+      false
+    };
+
     Self {
       dlx,
       partial_solution: Vec::new(),
+      levels: Vec::new(),
+      floor: 0,
       state: DlxExplorerState::NotStarted,
+      options,
+      nodes: 0,
+      status: SearchStatus::Running,
+      stats,
+      initial_updates,
+      prev_count_updates,
+      shuffled,
       _phantom: PhantomData,
     }
   }
 
+  fn status(&self) -> SearchStatus {
+    self.status
+  }
+
+  fn stats(&self) -> Option<&SearchStats> {
+    self.stats.as_ref()
+  }
+
   fn partial_solution(&self) -> &Vec<usize> {
     &self.partial_solution
   }
@@ -964,19 +1482,39 @@ where
     self.dlx.borrow()
   }
 
-  fn dlx_mut(&mut self) -> &mut Dlx<I, N> {
-    self.dlx.borrow_mut()
-  }
-
   #[must_use]
   fn choose_next_item(&mut self) -> ChooseNextItemResult {
-    let dlx = self.dlx_mut();
+    let dlx = self.dlx.borrow_mut();
+    let depth = self.partial_solution.len();
+    let item = match &mut self.options.chooser {
+      Some(chooser) => chooser
+        .choose(&mut dlx.item_candidates())
+        .map(|candidate| candidate.idx()),
+      None => dlx.choose_item(),
+    };
+    // A chooser giving up early would make the search report a partial cover
+    // as a solution.
+    assert!(
+      item.is_some() || dlx.header(0).node.next == 0,
+      "ItemChooser returned None while primary items remain uncovered"
+    );
+    if let Some(stats) = &mut self.stats {
+      stats.record_node(depth);
+      match item {
+        Some(item) => stats.record_branch(depth, dlx.branching_factor(item as usize)),
+        None => stats.record_solution(),
+      }
+    }
 
-    match dlx.choose_item() {
+    match item {
       Some(item) => {
         let item = item as usize;
-        dlx.cover(item);
+        let first_tweak = dlx.enter_item(item);
         self.partial_solution.push(item);
+        self.levels.push(SearchLevel {
+          first_tweak,
+          exhausted: false,
+        });
         ChooseNextItemResult::Continue
       }
       None => ChooseNextItemResult::FoundSolution,
@@ -985,57 +1523,108 @@ where
 
   #[must_use]
   fn explore_next_choice(&mut self) -> ExploreNextChoiceResult {
-    while let Some(p) = self.partial_solution.pop() {
-      let dlx = self.dlx_mut();
+    while self.partial_solution.len() > self.floor {
+      let p = self.partial_solution.pop().unwrap();
+      let level = self.levels.pop().unwrap();
+      let dlx = self.dlx.borrow_mut();
 
-      if let Node::Normal {
-        node_type: NodeType::Body { .. },
-        ..
-      } = dlx.node(p)
-      {
-        dlx.uncover_remaining_choices(p);
-      }
-
-      // Try exploring the next choice.
-      let p = dlx.node(p).next();
-
-      match dlx.node(p) {
+      let item = match dlx.node(p) {
         Node::Normal {
-          node_type: NodeType::Header { .. },
+          node_type: NodeType::Body { top, .. },
           ..
         } => {
-          // We have exhausted all options under this item, so continue to the
-          // previous item.
-          dlx.uncover(p);
+          let top = *top as usize;
+          dlx.uncover_remaining_choices(p);
+          top
         }
         Node::Normal {
-          node_type: NodeType::Body { .. },
+          node_type: NodeType::Header { .. },
           ..
         } => {
-          // We can try exploring this subset.
-          dlx.cover_remaining_choices(p);
-          self.partial_solution.push(p);
-          return ExploreNextChoiceResult::Continue;
+          if level.exhausted {
+            // We have already tried leaving this item without any more
+            // subsets, so continue to the previous item.
+            dlx.leave_item(p, level.first_tweak, true);
+            continue;
+          }
+          p
         }
         Node::Boundary { .. } => dlx_unreachable!("Unexpected boundary node found in queue: {p}"),
+      };
+
+      // Try exploring the next choice.
+      let p = dlx.node(p).next();
+      let header = dlx.header(item);
+      let (bound, slack) = (header.bound, header.slack);
+
+      if bound == 0 && slack == 0 {
+        // This is the last time the item can be covered, and it must be
+        // covered, so no tweaking is necessary.
+        if p == item {
+          // We have exhausted all options under this item, so continue to
+          // the previous item.
+          dlx.leave_item(item, level.first_tweak, false);
+          continue;
+        }
+      } else if dlx.body_header(item).len() + slack as usize <= bound as usize {
+        // There aren't enough subsets left to cover this item the minimum
+        // number of times.
+        dlx.leave_item(item, level.first_tweak, false);
+        continue;
+      } else if p != item {
+        dlx.tweak(p, item);
+      } else {
+        // Try covering this item no more times.
+        if bound != 0 {
+          dlx.unlink_item(item);
+        }
+        self.partial_solution.push(item);
+        self.levels.push(SearchLevel {
+          exhausted: true,
+          ..level
+        });
+        return ExploreNextChoiceResult::Continue;
       }
+
+      // We can try exploring this subset.
+      dlx.cover_remaining_choices(p);
+      self.partial_solution.push(p);
+      self.levels.push(level);
+      return ExploreNextChoiceResult::Continue;
     }
 
     ExploreNextChoiceResult::Done
   }
 
   fn step(&mut self) -> DlxStepResult<'_> {
-    // This should only be false the very first call to `next()`, or if
-    // `next()` is called after `None` is returned at the end of iteration.
+    if self.status != SearchStatus::Running {
+      return DlxStepResult::Done;
+    }
+    if let Some(status) = self.options.exceeded_limit(self.nodes) {
+      // Leave the partial solution in place, the `Drop` impl will restore the
+      // `Dlx`.
+      self.status = status;
+      return DlxStepResult::Done;
+    }
+    self.nodes += 1;
+
+    // This should only be false the very first call to `next()`.
     if matches!(self.state, DlxExplorerState::Started) {
       if let ExploreNextChoiceResult::Done = self.explore_next_choice() {
+        self.status = SearchStatus::Exhausted;
         return DlxStepResult::Done;
       }
     } else {
       self.state = DlxExplorerState::Started;
     }
 
-    if let ChooseNextItemResult::FoundSolution = self.choose_next_item() {
+    let result = self.choose_next_item();
+    if let Some(stats) = &mut self.stats {
+      let updates = self.dlx.borrow().updates;
+      stats.set_updates(updates.wrapping_sub(self.initial_updates));
+    }
+
+    if let ChooseNextItemResult::FoundSolution = result {
       return DlxStepResult::FoundSolution(&self.partial_solution);
     }
 
@@ -1050,25 +1639,48 @@ where
   fn drop(&mut self) {
     // Undo all changes we've made to the data structure before dropping,
     // leaving it unmodified.
-    self.partial_solution.clone().iter().rev().for_each(|&p| {
-      let dlx = self.dlx_mut();
+    while let Some(p) = self.partial_solution.pop() {
+      let level = self.levels.pop().unwrap();
+      let dlx = self.dlx.borrow_mut();
       if let Node::Normal {
-        node_type: NodeType::Body { .. },
+        node_type: NodeType::Body { top, .. },
         ..
       } = dlx.node(p)
       {
+        let top = *top as usize;
         dlx.uncover_remaining_choices(p);
-        dlx.uncover(dlx.to_top(p));
+        dlx.leave_item(top, level.first_tweak, false);
       } else {
-        dlx.uncover(p);
+        dlx.leave_item(p, level.first_tweak, level.exhausted);
       }
-    });
+    }
+
+    if self.shuffled {
+      self.dlx.borrow_mut().unshuffle_item_lists();
+    }
+    self.dlx.borrow_mut().count_updates = self.prev_count_updates;
   }
 }
 
 pub trait DlxIterator<I, N, R = Vec<usize>>: Iterator<Item = R> + Sized {
   fn dlx(&self) -> &Dlx<I, N>;
 
+  /// Reports whether the search is still running, or why it stopped. Once
+  /// this is no longer `SearchStatus::Running`, the iterator will not return
+  /// any more results.
+  fn status(&self) -> SearchStatus;
+
+  /// Returns the statistics collected so far, if the search was started with
+  /// `SearchOptions::with_stats`.
+  fn stats(&self) -> Option<&SearchStats>;
+
+  /// Snapshots where the search is, so that it can be picked up later with
+  /// `Dlx::resume_solutions`, even in another process.
+  fn checkpoint(&self) -> SearchCheckpoint
+  where
+    I: CheckpointValue,
+    N: CheckpointValue;
+
   fn mapped<F, S>(self, f: F) -> impl DlxIterator<I, N, S>
   where
     F: FnMut(&Dlx<I, N>, R) -> S,
@@ -1087,22 +1699,7 @@ where
   N: Clone,
 {
   fn with_names(self) -> impl DlxIterator<I, N, Vec<N>> {
-    self.mapped(|dlx, solution| {
-      solution
-        .into_iter()
-        .filter_map(|p| {
-          if let Node::Normal {
-            node_type: NodeType::Body { .. },
-            ..
-          } = dlx.node(p)
-          {
-            Some(dlx.set_name_for_node(p))
-          } else {
-            None
-          }
-        })
-        .collect()
-    })
+    self.mapped(|dlx, solution| dlx.solution_names(&solution))
   }
 }
 
@@ -1114,24 +1711,7 @@ where
   fn with_names(self) -> impl DlxIterator<I, N, StepwiseDlxIterResult<Vec<N>>> {
     self.mapped(|dlx, solution| {
       let is_step = matches!(solution, StepwiseDlxIterResult::Step(_));
-      let solution_vec = match solution {
-        StepwiseDlxIterResult::Step(solution) | StepwiseDlxIterResult::Solution(solution) => {
-          solution
-            .into_iter()
-            .filter_map(|p| {
-              if let Node::Normal {
-                node_type: NodeType::Body { .. },
-                ..
-              } = dlx.node(p)
-              {
-                Some(dlx.set_name_for_node(p))
-              } else {
-                None
-              }
-            })
-            .collect()
-        }
-      };
+      let solution_vec = dlx.solution_names(solution.result());
 
       if is_step {
         StepwiseDlxIterResult::Step(solution_vec)
@@ -1143,6 +1723,9 @@ where
 }
 
 pub trait DlxIteratorWithColors<I, N> {
+  /// Maps each solution to the colors it gives its secondary items. Items
+  /// used without a color (`Constraint::SecondaryUncolored`) or not at all
+  /// are left out.
   fn with_colors(self) -> impl DlxIterator<I, N, HashMap<I, u32>>;
 }
 
@@ -1192,8 +1775,12 @@ where
   D: BorrowMut<Dlx<I, N>>,
 {
   fn new(dlx: D) -> Self {
+    Self::with_options(dlx, SearchOptions::default())
+  }
+
+  fn with_options(dlx: D, options: SearchOptions<I>) -> Self {
     Self {
-      explorer: DlxExplorer::new(dlx),
+      explorer: DlxExplorer::with_options(dlx, options),
     }
   }
 }
@@ -1224,6 +1811,22 @@ where
   fn dlx(&self) -> &Dlx<I, N> {
     self.explorer.dlx()
   }
+
+  fn status(&self) -> SearchStatus {
+    self.explorer.status()
+  }
+
+  fn stats(&self) -> Option<&SearchStats> {
+    self.explorer.stats()
+  }
+
+  fn checkpoint(&self) -> SearchCheckpoint
+  where
+    I: CheckpointValue,
+    N: CheckpointValue,
+  {
+    self.explorer.checkpoint()
+  }
 }
 
 #[derive(Clone, Debug)]
@@ -1261,8 +1864,12 @@ where
   D: BorrowMut<Dlx<I, N>>,
 {
   fn new(dlx: D) -> Self {
+    Self::with_options(dlx, SearchOptions::default())
+  }
+
+  fn with_options(dlx: D, options: SearchOptions<I>) -> Self {
     Self {
-      explorer: DlxExplorer::new(dlx),
+      explorer: DlxExplorer::with_options(dlx, options),
     }
   }
 }
@@ -1294,6 +1901,22 @@ where
   fn dlx(&self) -> &Dlx<I, N> {
     self.explorer.dlx()
   }
+
+  fn status(&self) -> SearchStatus {
+    self.explorer.status()
+  }
+
+  fn stats(&self) -> Option<&SearchStats> {
+    self.explorer.stats()
+  }
+
+  fn checkpoint(&self) -> SearchCheckpoint
+  where
+    I: CheckpointValue,
+    N: CheckpointValue,
+  {
+    self.explorer.checkpoint()
+  }
 }
 
 #[derive(Debug)]
@@ -1344,10 +1967,33 @@ where
   fn dlx(&self) -> &Dlx<I, N> {
     self.iter.dlx()
   }
+
+  fn status(&self) -> SearchStatus {
+    self.iter.status()
+  }
+
+  fn stats(&self) -> Option<&SearchStats> {
+    self.iter.stats()
+  }
+
+  fn checkpoint(&self) -> SearchCheckpoint
+  where
+    I: CheckpointValue,
+    N: CheckpointValue,
+  {
+    self.iter.checkpoint()
+  }
 }
 
 #[cfg(test)]
 mod test {
+  use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{atomic::AtomicBool, mpsc, Arc},
+    thread,
+    time::{Duration, Instant},
+  };
+
   use googletest::gtest;
   use itertools::Itertools;
 
@@ -1355,7 +2001,8 @@ mod test {
 
   use crate::{
     dlx::{ColorItem, Constraint},
-    DlxIteratorWithNames, StepwiseDlxIterResult,
+    CancellationToken, DlxError, DlxIterator, DlxIteratorWithColors, DlxIteratorWithNames,
+    SearchOptions, SearchStats, SearchStatus, StepwiseDlxIterResult,
   };
 
   use super::{Dlx, HeaderType};
@@ -1475,6 +2122,63 @@ mod test {
       .is_some_and(|solution| { solution.into_iter().sorted().eq(vec![0, 3].into_iter()) }));
   }
 
+  #[gtest]
+  fn test_uncolored_secondary() {
+    let mut dlx = Dlx::new(
+      vec![
+        ('p', HeaderType::Primary),
+        ('q', HeaderType::Primary),
+        ('r', HeaderType::Primary),
+        ('a', HeaderType::Secondary),
+      ],
+      vec![
+        (0, vec!['p'.into(), Constraint::SecondaryUncolored('a')]),
+        (1, vec!['q'.into(), ColorItem::new('a', 1).into()]),
+        (2, vec!['r'.into(), ColorItem::new('a', 1).into()]),
+        (3, vec!['q'.into()]),
+        (4, vec!['r'.into()]),
+        (5, vec!['p'.into()]),
+      ],
+    );
+
+    // Subset 0 uses 'a' exclusively, while 1 and 2 may share it.
+    expect_that!(
+      dlx
+        .find_solutions()
+        .with_names()
+        .map(|solution| solution.into_iter().sorted().collect_vec())
+        .sorted()
+        .collect_vec(),
+      elements_are![
+        eq(&[0, 3, 4]),
+        eq(&[1, 2, 5]),
+        eq(&[1, 4, 5]),
+        eq(&[2, 3, 5]),
+        eq(&[3, 4, 5]),
+      ]
+    );
+    expect_that!(
+      dlx.subsets().next(),
+      some(eq(&(
+        &0,
+        vec![
+          Constraint::Primary('p'),
+          Constraint::SecondaryUncolored('a')
+        ]
+      )))
+    );
+
+    let colors = dlx.find_solutions().with_colors().collect_vec();
+    expect_eq!(colors.iter().filter(|colors| colors.is_empty()).count(), 2);
+    expect_that!(
+      colors
+        .iter()
+        .filter(|colors| !colors.is_empty())
+        .collect_vec(),
+      each(eq(&&HashMap::from([('a', 1)])))
+    );
+  }
+
   #[gtest]
   fn test_stepwise() {
     let mut dlx = Dlx::new(
@@ -1542,4 +2246,476 @@ mod test {
     );
     assert_that!(stepwise_iter.next(), none());
   }
+
+  #[gtest]
+  fn test_try_new_duplicate_item() {
+    let result = Dlx::try_new(
+      vec![('p', HeaderType::Primary), ('p', HeaderType::Secondary)],
+      vec![(0, vec!['p'])],
+    );
+    expect_that!(result, err(eq(&DlxError::DuplicateItem('p'))));
+  }
+
+  #[gtest]
+  fn test_try_new_duplicate_subset_name() {
+    let result = Dlx::try_new(
+      vec![('p', HeaderType::Primary), ('q', HeaderType::Primary)],
+      vec![(0, vec!['p']), (0, vec!['q'])],
+    );
+    expect_that!(result, err(eq(&DlxError::DuplicateSubsetName(0))));
+  }
+
+  #[gtest]
+  fn test_try_new_unknown_item() {
+    let result = Dlx::try_new(
+      vec![('p', HeaderType::Primary)],
+      vec![(0, vec!['p']), (1, vec!['p', 'q'])],
+    );
+    expect_that!(
+      result,
+      err(eq(&DlxError::UnknownItem {
+        subset: 1,
+        item: 'q'
+      }))
+    );
+  }
+
+  #[gtest]
+  fn test_try_new_constraint_type_mismatch() {
+    let result = Dlx::try_new(
+      vec![('p', HeaderType::Primary), ('a', HeaderType::Secondary)],
+      vec![(0, vec![Constraint::Primary('p'), Constraint::Primary('a')])],
+    );
+    expect_that!(
+      result,
+      err(eq(&DlxError::ConstraintTypeMismatch {
+        subset: 0,
+        item: 'a',
+        header_type: HeaderType::Secondary,
+      }))
+    );
+
+    let result = Dlx::try_new(
+      vec![('p', HeaderType::Primary)],
+      vec![(0, vec![Constraint::Secondary(ColorItem::new('p', 1))])],
+    );
+    expect_that!(
+      result,
+      err(eq(&DlxError::ConstraintTypeMismatch {
+        subset: 0,
+        item: 'p',
+        header_type: HeaderType::Primary,
+      }))
+    );
+
+    let result = Dlx::try_new(
+      vec![('p', HeaderType::Primary)],
+      vec![(0, vec![Constraint::SecondaryUncolored('p')])],
+    );
+    expect_that!(
+      result,
+      err(eq(&DlxError::ConstraintTypeMismatch {
+        subset: 0,
+        item: 'p',
+        header_type: HeaderType::Primary,
+      }))
+    );
+  }
+
+  #[test]
+  #[should_panic(expected = "Unknown item 'q' in subset 0")]
+  fn test_new_panics_on_invalid_input() {
+    Dlx::new(vec![('p', HeaderType::Primary)], vec![(0, vec!['q'])]);
+  }
+
+  #[gtest]
+  fn test_try_new_invalid_bounds() {
+    let result = Dlx::try_new(
+      vec![('p', HeaderType::BoundedPrimary { min: 2, max: 1 })],
+      vec![(0, vec!['p'])],
+    );
+    expect_that!(
+      result,
+      err(eq(&DlxError::InvalidBounds {
+        item: 'p',
+        min: 2,
+        max: 1
+      }))
+    );
+
+    let result = Dlx::try_new(
+      vec![('p', HeaderType::BoundedPrimary { min: 0, max: 0 })],
+      vec![(0, vec!['p'])],
+    );
+    expect_that!(
+      result,
+      err(eq(&DlxError::InvalidBounds {
+        item: 'p',
+        min: 0,
+        max: 0
+      }))
+    );
+  }
+
+  #[gtest]
+  fn test_bounded_primary() {
+    let mut dlx = Dlx::new(
+      vec![
+        ('a', HeaderType::BoundedPrimary { min: 2, max: 3 }),
+        ('b', HeaderType::Primary),
+      ],
+      vec![
+        (0, vec!['a']),
+        (1, vec!['a']),
+        (2, vec!['a']),
+        (3, vec!['a', 'b']),
+      ],
+    );
+
+    let solutions = dlx
+      .find_solutions()
+      .with_names()
+      .map(|solution| solution.into_iter().sorted().collect_vec())
+      .sorted()
+      .collect_vec();
+    expect_that!(
+      solutions,
+      elements_are![
+        elements_are![&0, &1, &3],
+        elements_are![&0, &2, &3],
+        elements_are![&0, &3],
+        elements_are![&1, &2, &3],
+        elements_are![&1, &3],
+        elements_are![&2, &3],
+      ]
+    );
+  }
+
+  #[gtest]
+  fn test_bounded_primary_unsatisfiable() {
+    let mut dlx = Dlx::new(
+      vec![
+        ('a', HeaderType::BoundedPrimary { min: 3, max: 4 }),
+        ('b', HeaderType::Primary),
+      ],
+      vec![(0, vec!['a']), (1, vec!['a', 'b']), (2, vec!['b'])],
+    );
+
+    expect_that!(dlx.find_solutions().collect_vec(), empty());
+  }
+
+  pub(crate) type Items = Vec<(u32, HeaderType)>;
+  pub(crate) type Subsets = Vec<(u32, Vec<Constraint<u32>>)>;
+
+  /// Finds all solutions to the given problem by checking every combination
+  /// of subsets, returning each as a sorted list of subset names.
+  fn brute_force_solutions(items: &Items, subsets: &Subsets) -> Vec<Vec<u32>> {
+    (0..subsets.len())
+      .powerset()
+      .filter(|choices| {
+        items.iter().all(|(item, header_type)| {
+          let constraints = choices
+            .iter()
+            .flat_map(|&choice| &subsets[choice].1)
+            .filter(|constraint| constraint.item() == item)
+            .collect_vec();
+          let count = constraints.len() as u32;
+          match header_type {
+            HeaderType::Primary => count == 1,
+            HeaderType::BoundedPrimary { min, max } => (*min..=*max).contains(&count),
+            HeaderType::Secondary => {
+              count <= 1
+                || constraints
+                  .iter()
+                  .map(|constraint| constraint.color())
+                  .all(|color| color.is_some() && color == constraints[0].color())
+            }
+          }
+        })
+      })
+      .map(|choices| {
+        choices
+          .into_iter()
+          .map(|choice| subsets[choice].0)
+          .collect()
+      })
+      .sorted()
+      .collect()
+  }
+
+  /// Generates a small random problem with a mix of primary, bounded primary
+  /// and secondary items, where every subset has at least one primary item.
+  pub(crate) fn random_problem(seed: u64) -> (Items, Subsets) {
+    let mut state = seed
+      .wrapping_mul(6364136223846793005)
+      .wrapping_add(1442695040888963407);
+    let mut rand = move |n: u32| {
+      state = state
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+      ((state >> 33) % n as u64) as u32
+    };
+
+    let items = (0..5)
+      .map(|item| {
+        let header_type = match rand(4) {
+          // Keep the first item primary so every subset can have one.
+          _ if item == 0 => HeaderType::BoundedPrimary { min: 1, max: 2 },
+          0 => HeaderType::Primary,
+          1 => HeaderType::Secondary,
+          _ => {
+            let min = rand(3);
+            HeaderType::BoundedPrimary {
+              min,
+              max: (min + rand(3)).max(1),
+            }
+          }
+        };
+        (item, header_type)
+      })
+      .collect_vec();
+    let subsets = (0..(6 + rand(5)))
+      .map(|name| {
+        let mut constraints = Vec::new();
+        for &(item, header_type) in &items {
+          if rand(3) != 0 {
+            continue;
+          }
+          constraints.push(match header_type {
+            HeaderType::Secondary => match rand(3) {
+              0 => Constraint::SecondaryUncolored(item),
+              color => ColorItem::new(item, color - 1).into(),
+            },
+            _ => Constraint::Primary(item),
+          });
+        }
+        // Subsets can only be chosen through one of their primary items.
+        if !constraints
+          .iter()
+          .any(|constraint| matches!(constraint, Constraint::Primary(_)))
+        {
+          constraints.push(Constraint::Primary(0));
+        }
+        (name, constraints)
+      })
+      .collect_vec();
+    (items, subsets)
+  }
+
+  #[gtest]
+  fn test_bounded_primary_matches_brute_force() {
+    for seed in 0..300 {
+      let (items, subsets) = random_problem(seed);
+      let mut dlx = Dlx::new(items.clone(), subsets.clone());
+
+      let solutions = dlx
+        .find_solutions()
+        .with_names()
+        .map(|solution| solution.into_iter().sorted().collect_vec())
+        .sorted()
+        .collect_vec();
+      expect_eq!(
+        solutions,
+        brute_force_solutions(&items, &subsets),
+        "seed {seed}"
+      );
+
+      // The search must leave the problem unmodified.
+      expect_eq!(dlx.find_solutions().count(), solutions.len(), "seed {seed}");
+    }
+  }
+
+  #[gtest]
+  fn test_count_solutions() {
+    let mut dlx = Dlx::new(
+      vec![
+        ('p', HeaderType::Primary),
+        ('q', HeaderType::Primary),
+        ('r', HeaderType::Primary),
+      ],
+      vec![
+        (0, vec!['p', 'q']),
+        (1, vec!['p']),
+        (2, vec!['p', 'q']),
+        (3, vec!['r']),
+      ],
+    );
+
+    expect_eq!(dlx.count_solutions(), 2);
+    expect_eq!(dlx.count_solutions_up_to(0), 0);
+    expect_eq!(dlx.count_solutions_up_to(1), 1);
+    expect_eq!(dlx.count_solutions_up_to(3), 2);
+    expect_eq!(dlx.count_solutions(), 2);
+  }
+
+  #[gtest]
+  fn test_count_solutions_matches_find_solutions() {
+    for seed in 0..100 {
+      let (items, subsets) = random_problem(seed);
+      let mut dlx = Dlx::new(items, subsets);
+
+      let count = dlx.find_solutions().count() as u64;
+      expect_eq!(dlx.count_solutions(), count, "seed {seed}");
+      expect_eq!(dlx.count_solutions_up_to(2), count.min(2), "seed {seed}");
+    }
+  }
+
+  /// Covers 8 items with singletons and pairs, which has many solutions.
+  pub(crate) fn singletons_and_pairs() -> Dlx<u32, usize> {
+    let subsets = (0..8)
+      .map(|item| vec![item])
+      .chain((0..8).tuple_combinations().map(|(a, b)| vec![a, b]))
+      .enumerate()
+      .collect_vec();
+    Dlx::new((0..8).map(|item| (item, HeaderType::Primary)), subsets)
+  }
+
+  #[gtest]
+  fn test_search_status() {
+    let mut dlx = singletons_and_pairs();
+
+    let mut solutions = dlx.find_solutions().with_names();
+    expect_eq!(solutions.status(), SearchStatus::Running);
+    expect_eq!(solutions.by_ref().count(), 764);
+    expect_eq!(solutions.status(), SearchStatus::Exhausted);
+    expect_that!(solutions.next(), none());
+    expect_eq!(solutions.status(), SearchStatus::Exhausted);
+  }
+
+  #[gtest]
+  fn test_node_budget() {
+    let mut dlx = singletons_and_pairs();
+
+    let mut solutions = dlx.find_solutions_with(SearchOptions::new().with_node_budget(100));
+    expect_that!(solutions.by_ref().count(), lt(764));
+    expect_eq!(solutions.status(), SearchStatus::NodeBudgetExceeded);
+    expect_that!(solutions.next(), none());
+    drop(solutions);
+
+    let mut steps = dlx.find_solutions_stepwise_with(SearchOptions::new().with_node_budget(10));
+    expect_eq!(steps.by_ref().count(), 10);
+    expect_eq!(steps.status(), SearchStatus::NodeBudgetExceeded);
+    drop(steps);
+
+    let mut solutions = dlx.find_solutions_with(SearchOptions::new().with_node_budget(1_000_000));
+    expect_eq!(solutions.by_ref().count(), 764);
+    expect_eq!(solutions.status(), SearchStatus::Exhausted);
+    drop(solutions);
+
+    // The search must leave the problem unmodified.
+    expect_eq!(dlx.count_solutions(), 764);
+  }
+
+  #[gtest]
+  fn test_deadline() {
+    let mut dlx = singletons_and_pairs();
+
+    let mut solutions = dlx.find_solutions_with(SearchOptions::new().with_deadline(Instant::now()));
+    expect_that!(solutions.next(), none());
+    expect_eq!(solutions.status(), SearchStatus::DeadlineExceeded);
+    drop(solutions);
+
+    let mut solutions = dlx
+      .find_solutions_with(SearchOptions::new().with_timeout(Duration::from_secs(3600)))
+      .with_names();
+    expect_eq!(solutions.by_ref().count(), 764);
+    expect_eq!(solutions.status(), SearchStatus::Exhausted);
+    drop(solutions);
+
+    let mut solutions = dlx.into_solutions_with(SearchOptions::new().with_deadline(Instant::now()));
+    expect_that!(solutions.next(), none());
+    expect_eq!(solutions.status(), SearchStatus::DeadlineExceeded);
+  }
+
+  #[gtest]
+  fn test_cancellation() {
+    let mut dlx = singletons_and_pairs();
+    let token = CancellationToken::new();
+
+    let mut solutions = dlx
+      .find_solutions_with(SearchOptions::new().with_cancellation(token.clone()))
+      .with_names();
+    expect_eq!(solutions.by_ref().take(3).count(), 3);
+    expect_eq!(solutions.status(), SearchStatus::Running);
+    token.cancel();
+    expect_that!(solutions.next(), none());
+    expect_eq!(solutions.status(), SearchStatus::Cancelled);
+    drop(solutions);
+
+    // Cancelling must leave the problem unmodified.
+    expect_eq!(dlx.count_solutions(), 764);
+
+    let flag = Arc::new(AtomicBool::new(true));
+    let mut solutions =
+      dlx.find_solutions_with(SearchOptions::new().with_cancellation(flag.into()));
+    expect_that!(solutions.next(), none());
+    expect_eq!(solutions.status(), SearchStatus::Cancelled);
+  }
+
+  #[gtest]
+  fn test_cancellation_from_another_thread() {
+    let token = CancellationToken::new();
+    let (found_sender, found_receiver) = mpsc::channel();
+    let (resume_sender, resume_receiver) = mpsc::channel();
+    let search = thread::spawn({
+      let token = token.clone();
+      move || {
+        let mut solutions =
+          singletons_and_pairs().into_solutions_with(SearchOptions::new().with_cancellation(token));
+        let mut count = 0;
+        while solutions.next().is_some() {
+          count += 1;
+          // Wait for the other thread after the first solution.
+          if count == 1 {
+            found_sender.send(()).unwrap();
+            resume_receiver.recv().unwrap();
+          }
+        }
+        (count, solutions.status())
+      }
+    });
+
+    found_receiver.recv().unwrap();
+    token.cancel();
+    resume_sender.send(()).unwrap();
+    expect_eq!(search.join().unwrap(), (1, SearchStatus::Cancelled));
+  }
+
+  #[gtest]
+  fn test_search_stats() {
+    let mut dlx = singletons_and_pairs();
+    expect_that!(dlx.find_solutions().stats(), none());
+    // Searches without stats don't count updates.
+    expect_eq!(dlx.find_solutions().count(), 764);
+    expect_eq!(dlx.updates, 0);
+
+    let num_nodes = dlx.find_solutions_stepwise().count() as u64;
+    let mut steps = dlx.find_solutions_stepwise_with(SearchOptions::new().with_stats());
+    expect_that!(steps.stats(), some(eq(&SearchStats::default())));
+    expect_eq!(steps.by_ref().count() as u64, num_nodes);
+
+    let stats = steps.stats().unwrap();
+    expect_eq!(stats.nodes(), num_nodes);
+    expect_eq!(stats.solutions(), 764);
+    // All singletons.
+    expect_eq!(stats.max_depth(), 8);
+    // Every item is in 8 subsets, so the first item is chosen.
+    expect_that!(
+      stats.branching_factors().first(),
+      some(eq(&BTreeMap::from([(8, 1)])))
+    );
+    expect_eq!(
+      stats
+        .branching_factors()
+        .iter()
+        .flat_map(|histogram| histogram.values())
+        .sum::<u64>(),
+      num_nodes - 764
+    );
+    // Every hidden link has been restored by the end of the search.
+    expect_that!(stats.updates(), gt(0));
+    expect_eq!(stats.updates() % 2, 0);
+    drop(steps);
+    expect_false!(dlx.count_updates);
+  }
 }
@@ -0,0 +1,843 @@
+use std::{
+  borrow::BorrowMut,
+  error::Error,
+  fmt::{self, Debug, Display, Formatter},
+  hash::Hash,
+};
+
+use super::{
+  ColorItem, Constraint, Dlx, DlxExplorer, DlxExplorerState, DlxIterator, DlxIteratorImpl,
+  ExploreNextChoiceResult, HeaderType, Node, NodeType, SearchLevel, SearchOptions,
+  StepwiseDlxIterResult, StepwiseDlxIteratorImpl,
+};
+
+const MAGIC: &[u8; 4] = b"DLXC";
+const VERSION: u8 = 2;
+
+/// The ways resuming a search from a `SearchCheckpoint` can fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckpointError {
+  /// The bytes weren't written by `SearchCheckpoint::to_bytes`.
+  InvalidFormat,
+  /// The checkpoint was taken from a search of a different problem.
+  ProblemMismatch,
+  /// The checkpoint was taken from a search with different givens (see
+  /// `Dlx::with_givens`).
+  GivensMismatch,
+  /// The checkpoint was taken from a search with a different
+  /// `SearchOptions::with_seed`, which tries subsets in a different order.
+  SeedMismatch,
+  /// The search state in the checkpoint can't be reached in this problem.
+  InvalidState,
+}
+
+impl Display for CheckpointError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      CheckpointError::InvalidFormat => write!(f, "Not a search checkpoint"),
+      CheckpointError::ProblemMismatch => {
+        write!(f, "Checkpoint was taken from a different problem")
+      }
+      CheckpointError::GivensMismatch => {
+        write!(
+          f,
+          "Checkpoint was taken from a search with different givens"
+        )
+      }
+      CheckpointError::SeedMismatch => {
+        write!(
+          f,
+          "Checkpoint was taken from a search with a different seed"
+        )
+      }
+      CheckpointError::InvalidState => write!(f, "Checkpoint has an unreachable search state"),
+    }
+  }
+}
+
+impl Error for CheckpointError {}
+
+/// An item or subset name which can be stored in a `SearchCheckpoint`, so
+/// that the problem can be rebuilt from the checkpoint alone.
+pub trait CheckpointValue: Sized {
+  /// Appends the value to `bytes`.
+  fn encode(&self, bytes: &mut Vec<u8>);
+
+  /// Reads back a value written by `encode` from the front of `bytes`,
+  /// advancing past it. Returns None if `bytes` doesn't start with a valid
+  /// encoding.
+  fn decode(bytes: &mut &[u8]) -> Option<Self>;
+}
+
+/// Splits `len` bytes off the front of `bytes`.
+fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+  if bytes.len() < len {
+    return None;
+  }
+  let (front, rest) = bytes.split_at(len);
+  *bytes = rest;
+  Some(front)
+}
+
+macro_rules! checkpoint_value_for_ints {
+  ($($int:ty),*) => {
+    $(
+      impl CheckpointValue for $int {
+        fn encode(&self, bytes: &mut Vec<u8>) {
+          bytes.extend_from_slice(&self.to_le_bytes());
+        }
+
+        fn decode(bytes: &mut &[u8]) -> Option<Self> {
+          Some(Self::from_le_bytes(take(bytes, size_of::<Self>())?.try_into().unwrap()))
+        }
+      }
+    )*
+  };
+}
+
+checkpoint_value_for_ints!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl CheckpointValue for usize {
+  fn encode(&self, bytes: &mut Vec<u8>) {
+    (*self as u64).encode(bytes);
+  }
+
+  fn decode(bytes: &mut &[u8]) -> Option<Self> {
+    u64::decode(bytes)?.try_into().ok()
+  }
+}
+
+impl CheckpointValue for isize {
+  fn encode(&self, bytes: &mut Vec<u8>) {
+    (*self as i64).encode(bytes);
+  }
+
+  fn decode(bytes: &mut &[u8]) -> Option<Self> {
+    i64::decode(bytes)?.try_into().ok()
+  }
+}
+
+impl CheckpointValue for bool {
+  fn encode(&self, bytes: &mut Vec<u8>) {
+    bytes.push(*self as u8);
+  }
+
+  fn decode(bytes: &mut &[u8]) -> Option<Self> {
+    match u8::decode(bytes)? {
+      0 => Some(false),
+      1 => Some(true),
+      _ => None,
+    }
+  }
+}
+
+impl CheckpointValue for char {
+  fn encode(&self, bytes: &mut Vec<u8>) {
+    (*self as u32).encode(bytes);
+  }
+
+  fn decode(bytes: &mut &[u8]) -> Option<Self> {
+    char::from_u32(u32::decode(bytes)?)
+  }
+}
+
+impl CheckpointValue for String {
+  fn encode(&self, bytes: &mut Vec<u8>) {
+    self.len().encode(bytes);
+    bytes.extend_from_slice(self.as_bytes());
+  }
+
+  fn decode(bytes: &mut &[u8]) -> Option<Self> {
+    let len = usize::decode(bytes)?;
+    String::from_utf8(take(bytes, len)?.to_vec()).ok()
+  }
+}
+
+impl<T> CheckpointValue for Vec<T>
+where
+  T: CheckpointValue,
+{
+  fn encode(&self, bytes: &mut Vec<u8>) {
+    self.len().encode(bytes);
+    for value in self {
+      value.encode(bytes);
+    }
+  }
+
+  fn decode(bytes: &mut &[u8]) -> Option<Self> {
+    let len = usize::decode(bytes)?;
+    // Don't trust the length with an allocation before the values are read.
+    let mut values = Vec::with_capacity(len.min(bytes.len()));
+    for _ in 0..len {
+      values.push(T::decode(bytes)?);
+    }
+    Some(values)
+  }
+}
+
+macro_rules! checkpoint_value_for_tuples {
+  ($(($($name:ident),*)),*) => {
+    $(
+      impl<$($name),*> CheckpointValue for ($($name,)*)
+      where
+        $($name: CheckpointValue),*
+      {
+        #[allow(non_snake_case)]
+        fn encode(&self, bytes: &mut Vec<u8>) {
+          let ($($name,)*) = self;
+          $($name.encode(bytes);)*
+        }
+
+        fn decode(bytes: &mut &[u8]) -> Option<Self> {
+          Some(($($name::decode(bytes)?,)*))
+        }
+      }
+    )*
+  };
+}
+
+checkpoint_value_for_tuples!((A, B), (A, B, C), (A, B, C, D));
+
+/// One level of the search stack in a checkpoint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct CheckpointLevel {
+  /// The entry of `DlxExplorer::partial_solution` at this level.
+  entry: usize,
+  exhausted: bool,
+}
+
+/// A snapshot of where a search is, from `DlxIterator::checkpoint`, from
+/// which the search can be picked up again with `Dlx::resume_solutions`.
+///
+/// The checkpoint contains the whole problem, with its item types, colors
+/// and costs, along with the subsets given with `Dlx::with_givens`, so the
+/// search can be resumed in another process from the checkpoint alone, with
+/// `Dlx::from_checkpoint`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchCheckpoint {
+  /// The problem, as encoded by `Dlx::encode_problem`.
+  problem: Vec<u8>,
+  /// The first node of each given subset, in the order they were forced.
+  givens: Vec<usize>,
+  seed: Option<u64>,
+  started: bool,
+  levels: Vec<CheckpointLevel>,
+}
+
+/// Reads checkpoint fields from the front of a byte slice.
+struct Reader<'a> {
+  bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+  fn read<T: CheckpointValue>(&mut self) -> Result<T, CheckpointError> {
+    T::decode(&mut self.bytes).ok_or(CheckpointError::InvalidFormat)
+  }
+
+  fn take(&mut self, len: usize) -> Result<&'a [u8], CheckpointError> {
+    take(&mut self.bytes, len).ok_or(CheckpointError::InvalidFormat)
+  }
+
+  fn finish(&self) -> Result<(), CheckpointError> {
+    if self.bytes.is_empty() {
+      Ok(())
+    } else {
+      Err(CheckpointError::InvalidFormat)
+    }
+  }
+}
+
+impl SearchCheckpoint {
+  /// The number of subsets in the partial solution the search was at.
+  pub fn depth(&self) -> usize {
+    self.levels.len()
+  }
+
+  /// Encodes the checkpoint in a compact binary format, which can be read
+  /// back with `SearchCheckpoint::from_bytes`.
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut bytes =
+      Vec::with_capacity(32 + self.problem.len() + 8 * self.givens.len() + 9 * self.levels.len());
+    bytes.extend_from_slice(MAGIC);
+    VERSION.encode(&mut bytes);
+    self.problem.len().encode(&mut bytes);
+    bytes.extend_from_slice(&self.problem);
+    self.givens.encode(&mut bytes);
+    self.seed.is_some().encode(&mut bytes);
+    self.seed.unwrap_or(0).encode(&mut bytes);
+    self.started.encode(&mut bytes);
+    self.levels.len().encode(&mut bytes);
+    for level in &self.levels {
+      level.entry.encode(&mut bytes);
+      level.exhausted.encode(&mut bytes);
+    }
+    bytes
+  }
+
+  pub fn from_bytes(bytes: &[u8]) -> Result<Self, CheckpointError> {
+    let mut reader = Reader { bytes };
+    if reader.take(MAGIC.len())? != MAGIC || reader.read::<u8>()? != VERSION {
+      return Err(CheckpointError::InvalidFormat);
+    }
+    let problem_len = reader.read()?;
+    let problem = reader.take(problem_len)?.to_vec();
+    let givens = reader.read()?;
+    let has_seed = reader.read::<bool>()?;
+    let seed = reader.read()?;
+    let started = reader.read()?;
+    let num_levels = reader.read::<usize>()?;
+    let levels = (0..num_levels)
+      .map(|_| {
+        Ok(CheckpointLevel {
+          entry: reader.read()?,
+          exhausted: reader.read()?,
+        })
+      })
+      .collect::<Result<_, _>>()?;
+    reader.finish()?;
+
+    Ok(Self {
+      problem,
+      givens,
+      seed: has_seed.then_some(seed),
+      started,
+      levels,
+    })
+  }
+}
+
+impl<I, N> Dlx<I, N>
+where
+  I: CheckpointValue,
+  N: CheckpointValue,
+{
+  /// Encodes the problem: the items with their types, then the subsets in
+  /// the order they are tried, with their names, costs and constraints.
+  /// Constraints refer to items by their position, and only colors are
+  /// stored alongside, since the item type tells primary and uncolored
+  /// secondary constraints apart. None of this changes during a search.
+  fn encode_problem(&self) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    (self.headers.len() - 2).encode(&mut bytes);
+    for header in &self.headers[1..(self.headers.len() - 1)] {
+      header.item.as_ref().unwrap().encode(&mut bytes);
+      match header.header_type {
+        HeaderType::Primary => 0u8.encode(&mut bytes),
+        HeaderType::BoundedPrimary { min, max } => {
+          1u8.encode(&mut bytes);
+          min.encode(&mut bytes);
+          max.encode(&mut bytes);
+        }
+        HeaderType::Secondary => 2u8.encode(&mut bytes),
+      }
+    }
+
+    let mut subsets = Vec::new();
+    let mut num_subsets = 0usize;
+    let mut start = self.headers.len();
+    for idx in self.headers.len()..self.body.len() {
+      let Node::Boundary { name, cost, .. } = self.body_node(idx) else {
+        continue;
+      };
+      name.as_ref().unwrap().encode(&mut subsets);
+      cost.encode(&mut subsets);
+      (idx - start).encode(&mut subsets);
+      for q in start..idx {
+        let Node::Normal {
+          node_type: NodeType::Body { color, top, .. },
+          ..
+        } = self.body_node(q)
+        else {
+          dlx_unreachable!("Unexpected node in subset at index {q}");
+        };
+        (*top as usize - 1).encode(&mut subsets);
+        color.is_some().encode(&mut subsets);
+        if let Some(color) = color {
+          color.encode(&mut subsets);
+        }
+      }
+      num_subsets += 1;
+      start = idx + 1;
+    }
+    num_subsets.encode(&mut bytes);
+    bytes.extend_from_slice(&subsets);
+    bytes
+  }
+}
+
+impl<I, N> Dlx<I, N>
+where
+  I: CheckpointValue + Hash + Eq + Clone + Debug,
+  N: CheckpointValue + Hash + Eq + Clone + Debug,
+{
+  /// Rebuilds the problem `checkpoint` was taken from, returning it along
+  /// with the names of the subsets which were given with `Dlx::with_givens`
+  /// in that search. To resume the search, give the same subsets again, in
+  /// the same order, and resume through the returned `DlxWithGivens`, or
+  /// resume the problem directly if there were no givens.
+  pub fn from_checkpoint(checkpoint: &SearchCheckpoint) -> Result<(Self, Vec<N>), CheckpointError> {
+    let mut reader = Reader {
+      bytes: &checkpoint.problem,
+    };
+    let num_items = reader.read::<usize>()?;
+    let mut items = Vec::new();
+    for _ in 0..num_items {
+      let item = reader.read::<I>()?;
+      let header_type = match reader.read::<u8>()? {
+        0 => HeaderType::Primary,
+        1 => HeaderType::BoundedPrimary {
+          min: reader.read()?,
+          max: reader.read()?,
+        },
+        2 => HeaderType::Secondary,
+        _ => return Err(CheckpointError::InvalidFormat),
+      };
+      items.push((item, header_type));
+    }
+
+    let num_subsets = reader.read::<usize>()?;
+    let mut subsets = Vec::new();
+    for _ in 0..num_subsets {
+      let name = reader.read::<N>()?;
+      let cost = reader.read::<u64>()?;
+      let num_constraints = reader.read::<usize>()?;
+      let mut constraints = Vec::new();
+      for _ in 0..num_constraints {
+        let (item, header_type) = items
+          .get(reader.read::<usize>()?)
+          .ok_or(CheckpointError::InvalidFormat)?;
+        let item = item.clone();
+        constraints.push(match reader.read::<bool>()? {
+          true => ColorItem::new(item, reader.read()?).into(),
+          false if header_type.is_primary() => Constraint::Primary(item),
+          false => Constraint::SecondaryUncolored(item),
+        });
+      }
+      subsets.push((name, cost, constraints));
+    }
+    reader.finish()?;
+
+    // The subsets are stored in the order they are tried, so they must not
+    // be sorted by cost again.
+    let dlx = Self::construct(items, subsets).map_err(|_| CheckpointError::InvalidFormat)?;
+    let givens_valid = checkpoint.givens.iter().all(|&idx| {
+      (dlx.headers.len()..dlx.body.len()).contains(&idx)
+        && matches!(dlx.body_node(idx - 1), Node::Boundary { .. })
+        && matches!(dlx.body_node(idx), Node::Normal { .. })
+    });
+    if !givens_valid {
+      return Err(CheckpointError::InvalidFormat);
+    }
+    let givens = dlx.solution_names(&checkpoint.givens);
+    Ok((dlx, givens))
+  }
+}
+
+impl<I, N> Dlx<I, N> {
+  /// Returns the primary item a checkpoint entry branches on, if `entry` is
+  /// a primary item or a node of one.
+  fn checkpoint_item(&self, entry: usize) -> Option<usize> {
+    let item = if (1..=self.num_primary_items).contains(&entry) {
+      entry
+    } else if (self.headers.len()..self.body.len()).contains(&entry) {
+      match self.body_node(entry) {
+        Node::Normal {
+          node_type: NodeType::Body { top, .. },
+          ..
+        } if (*top as usize) <= self.num_primary_items => *top as usize,
+        _ => return None,
+      }
+    } else {
+      return None;
+    };
+
+    // The item must still be waiting to be covered.
+    let header = self.header(item);
+    (header.bound != 0 && self.header(header.node.prev as usize).node.next as usize == item)
+      .then_some(item)
+  }
+}
+
+impl<D, I, N> DlxExplorer<D, I, N>
+where
+  D: BorrowMut<Dlx<I, N>>,
+  I: CheckpointValue,
+  N: CheckpointValue,
+{
+  pub(super) fn checkpoint(&self) -> SearchCheckpoint {
+    SearchCheckpoint {
+      problem: self.dlx().encode_problem(),
+      givens: self.dlx().forced.clone(),
+      seed: self.options.seed,
+      started: matches!(self.state, DlxExplorerState::Started),
+      levels: self
+        .partial_solution
+        .iter()
+        .zip(&self.levels)
+        .map(|(&entry, level)| CheckpointLevel {
+          entry,
+          exhausted: level.exhausted,
+        })
+        .collect(),
+    }
+  }
+
+  /// Creates an explorer at the point in the search where `checkpoint` was
+  /// taken, by replaying the choices made at each level.
+  fn resume(
+    dlx: D,
+    options: SearchOptions<I>,
+    checkpoint: &SearchCheckpoint,
+  ) -> Result<Self, CheckpointError> {
+    if dlx.borrow().encode_problem() != checkpoint.problem {
+      return Err(CheckpointError::ProblemMismatch);
+    }
+    if dlx.borrow().forced != checkpoint.givens {
+      return Err(CheckpointError::GivensMismatch);
+    }
+    if options.seed != checkpoint.seed {
+      return Err(CheckpointError::SeedMismatch);
+    }
+    // If replaying fails, dropping the explorer undoes the levels replayed
+    // so far.
+    let mut explorer = Self::with_options(dlx, options);
+
+    for (depth, level) in checkpoint.levels.iter().enumerate() {
+      let dlx = explorer.dlx.borrow_mut();
+      let item = dlx
+        .checkpoint_item(level.entry)
+        .ok_or(CheckpointError::InvalidState)?;
+      let first_tweak = dlx.enter_item(item);
+      explorer.partial_solution.push(item);
+      explorer.levels.push(SearchLevel {
+        first_tweak,
+        exhausted: false,
+      });
+
+      if level.entry == item && !level.exhausted {
+        // The search had chosen this item, but not yet tried any subsets for
+        // it, which can only happen at the deepest level.
+        if depth + 1 != checkpoint.levels.len() {
+          return Err(CheckpointError::InvalidState);
+        }
+        continue;
+      }
+
+      explorer.floor = depth;
+      loop {
+        let ExploreNextChoiceResult::Continue = explorer.explore_next_choice() else {
+          return Err(CheckpointError::InvalidState);
+        };
+        if explorer.partial_solution.last() == Some(&level.entry)
+          && explorer.levels.last().unwrap().exhausted == level.exhausted
+        {
+          break;
+        }
+      }
+    }
+
+    explorer.floor = 0;
+    if checkpoint.started {
+      explorer.state = DlxExplorerState::Started;
+    }
+    Ok(explorer)
+  }
+}
+
+impl<I, N> Dlx<I, N>
+where
+  I: CheckpointValue,
+  N: CheckpointValue,
+{
+  /// Picks up the search from `checkpoint`, which must have been taken from
+  /// a search of an identical problem, with the same givens. The resumed search finds exactly the
+  /// solutions that the original search hadn't yet returned.
+  pub fn resume_solutions(
+    &mut self,
+    checkpoint: &SearchCheckpoint,
+  ) -> Result<impl DlxIterator<I, N> + '_, CheckpointError> {
+    self.resume_solutions_with(checkpoint, SearchOptions::default())
+  }
+
+  /// Like `resume_solutions`, but searching with `options`. If the original
+  /// search was seeded, `options` must have the same seed, though the
+  /// remaining solutions may come in a different order, since the random
+  /// tie-breaking between items starts over. The node budget and statistics
+  /// also start over from zero.
+  pub fn resume_solutions_with(
+    &mut self,
+    checkpoint: &SearchCheckpoint,
+    options: SearchOptions<I>,
+  ) -> Result<impl DlxIterator<I, N> + '_, CheckpointError> {
+    Ok(DlxIteratorImpl {
+      explorer: DlxExplorer::resume(self, options, checkpoint)?,
+    })
+  }
+
+  pub fn resume_solutions_stepwise_with(
+    &mut self,
+    checkpoint: &SearchCheckpoint,
+    options: SearchOptions<I>,
+  ) -> Result<impl DlxIterator<I, N, StepwiseDlxIterResult<Vec<usize>>> + '_, CheckpointError> {
+    Ok(StepwiseDlxIteratorImpl {
+      explorer: DlxExplorer::resume(self, options, checkpoint)?,
+    })
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use googletest::prelude::*;
+  use itertools::Itertools;
+
+  use crate::{
+    dlx::test::{random_problem, singletons_and_pairs},
+    CheckpointError, CheckpointValue, Dlx, DlxIterator, DlxIteratorWithNames, HeaderType,
+    SearchCheckpoint, SearchOptions, SearchStatus,
+  };
+
+  /// Runs the search in stretches of `budget` nodes, checkpointing through
+  /// bytes and resuming after each one.
+  fn solutions_in_stretches(
+    dlx: &mut Dlx<u32, u32>,
+    options: impl Fn() -> SearchOptions<u32>,
+    budget: u64,
+  ) -> Vec<Vec<u32>> {
+    let mut solutions = vec![];
+    let mut iter = dlx
+      .find_solutions_with(options().with_node_budget(budget))
+      .with_names();
+    solutions.extend(iter.by_ref());
+    let mut status = iter.status();
+    let mut bytes = iter.checkpoint().to_bytes();
+    drop(iter);
+
+    while status == SearchStatus::NodeBudgetExceeded {
+      let checkpoint = SearchCheckpoint::from_bytes(&bytes).unwrap();
+      let mut iter = dlx
+        .resume_solutions_with(&checkpoint, options().with_node_budget(budget))
+        .unwrap()
+        .with_names();
+      solutions.extend(iter.by_ref());
+      status = iter.status();
+      bytes = iter.checkpoint().to_bytes();
+    }
+    expect_eq!(status, SearchStatus::Exhausted);
+    solutions
+  }
+
+  #[gtest]
+  fn test_resume_finds_remaining_solutions() {
+    for seed in 0..50 {
+      let (items, subsets) = random_problem(seed);
+      let mut dlx = Dlx::new(items, subsets);
+      let expected = dlx.find_solutions().with_names().collect_vec();
+
+      for budget in [1, 2, 7] {
+        expect_eq!(
+          solutions_in_stretches(&mut dlx, SearchOptions::new, budget),
+          expected,
+          "seed {seed}, budget {budget}"
+        );
+      }
+      // Resuming must leave the problem unmodified.
+      expect_eq!(dlx.count_solutions(), expected.len() as u64);
+    }
+  }
+
+  #[gtest]
+  fn test_resume_seeded_search() {
+    let mut dlx = Dlx::new(
+      (0..6).map(|item| (item, HeaderType::Primary)),
+      (0..6)
+        .map(|item| vec![item])
+        .chain((0..6).tuple_combinations().map(|(a, b)| vec![a, b]))
+        .zip(0..)
+        .map(|(subset, name)| (name, subset)),
+    );
+    let expected = dlx
+      .find_solutions_with(SearchOptions::new().with_seed(5))
+      .with_names()
+      .map(|solution| solution.into_iter().sorted().collect_vec())
+      .sorted()
+      .collect_vec();
+
+    expect_eq!(
+      solutions_in_stretches(&mut dlx, || SearchOptions::new().with_seed(5), 10)
+        .into_iter()
+        .map(|solution| solution.into_iter().sorted().collect_vec())
+        .sorted()
+        .collect_vec(),
+      expected
+    );
+  }
+
+  #[gtest]
+  fn test_checkpoint_bytes_round_trip() {
+    let mut dlx = singletons_and_pairs();
+    let mut iter = dlx.find_solutions_with(SearchOptions::new().with_seed(3));
+    iter.nth(10);
+    let checkpoint = iter.checkpoint();
+    expect_that!(checkpoint.depth(), gt(0));
+
+    let bytes = checkpoint.to_bytes();
+    expect_eq!(SearchCheckpoint::from_bytes(&bytes), Ok(checkpoint));
+    expect_eq!(
+      SearchCheckpoint::from_bytes(&bytes[..bytes.len() - 1]),
+      Err(CheckpointError::InvalidFormat)
+    );
+    expect_eq!(
+      SearchCheckpoint::from_bytes(&[bytes.as_slice(), &[0]].concat()),
+      Err(CheckpointError::InvalidFormat)
+    );
+    expect_eq!(
+      SearchCheckpoint::from_bytes(b"nope"),
+      Err(CheckpointError::InvalidFormat)
+    );
+  }
+
+  #[gtest]
+  fn test_resume_errors() {
+    let mut dlx = singletons_and_pairs();
+    let mut iter = dlx.find_solutions();
+    iter.nth(10);
+    let checkpoint = iter.checkpoint();
+    drop(iter);
+
+    expect_that!(
+      dlx
+        .resume_solutions_with(&checkpoint, SearchOptions::new().with_seed(1))
+        .err(),
+      some(eq(CheckpointError::SeedMismatch))
+    );
+
+    let mut other = Dlx::new(
+      (0..8).map(|item| (item, HeaderType::Primary)),
+      (0..8).map(|item| (item, vec![item])),
+    );
+    expect_that!(
+      other.resume_solutions(&checkpoint).err(),
+      some(eq(CheckpointError::ProblemMismatch))
+    );
+
+    // A checkpoint whose choices can't be replayed must be rejected, and leave
+    // the problem unmodified.
+    let mut bytes = checkpoint.to_bytes();
+    let last = bytes.len() - 9;
+    bytes[last..(last + 8)].copy_from_slice(&0u64.to_le_bytes());
+    let corrupt = SearchCheckpoint::from_bytes(&bytes).unwrap();
+    expect_that!(
+      dlx.resume_solutions(&corrupt).err(),
+      some(eq(CheckpointError::InvalidState))
+    );
+    expect_eq!(dlx.count_solutions(), 764);
+  }
+
+  #[gtest]
+  fn test_checkpoint_value_round_trip() {
+    let value = (
+      ('x', -3i64, 7usize),
+      "naïve".to_owned(),
+      vec![(true, 1u8), (false, 255u8)],
+    );
+    let mut bytes = vec![];
+    value.encode(&mut bytes);
+    let mut rest = bytes.as_slice();
+    expect_eq!(CheckpointValue::decode(&mut rest), Some(value));
+    expect_that!(rest, empty());
+
+    expect_eq!(
+      String::decode(&mut &[3, 0, 0, 0, 0, 0, 0, 0, b'a'][..]),
+      None
+    );
+    expect_eq!(char::decode(&mut &0xd800u32.to_le_bytes()[..]), None);
+  }
+
+  #[gtest]
+  fn test_rebuild_from_checkpoint() {
+    for seed in 0..50 {
+      let (items, subsets) = random_problem(seed);
+      let mut dlx = Dlx::new_with_costs(
+        items,
+        subsets
+          .into_iter()
+          .map(|(name, constraints)| (name, (name as u64 * 7) % 5, constraints)),
+      );
+      let mut iter = dlx
+        .find_solutions_with(SearchOptions::new().with_node_budget(5))
+        .with_names();
+      iter.by_ref().count();
+      let bytes = iter.checkpoint().to_bytes();
+      drop(iter);
+      let remaining = dlx
+        .resume_solutions(&SearchCheckpoint::from_bytes(&bytes).unwrap())
+        .unwrap()
+        .with_names()
+        .collect_vec();
+
+      let checkpoint = SearchCheckpoint::from_bytes(&bytes).unwrap();
+      let (mut rebuilt, givens) = Dlx::<u32, u32>::from_checkpoint(&checkpoint).unwrap();
+      expect_that!(givens, empty());
+      expect_eq!(rebuilt.items().collect_vec(), dlx.items().collect_vec());
+      expect_eq!(
+        rebuilt.subsets().collect_vec(),
+        dlx.subsets().collect_vec(),
+        "seed {seed}"
+      );
+      expect_eq!(
+        rebuilt.find_min_cost_solutions(3),
+        dlx.find_min_cost_solutions(3),
+        "seed {seed}"
+      );
+      expect_eq!(
+        rebuilt
+          .resume_solutions(&checkpoint)
+          .unwrap()
+          .with_names()
+          .collect_vec(),
+        remaining,
+        "seed {seed}"
+      );
+    }
+  }
+
+  #[gtest]
+  fn test_resume_with_givens() {
+    let mut dlx = singletons_and_pairs();
+    let mut with_givens = dlx.with_givens(&[8]).unwrap();
+    let expected = with_givens.find_solutions().with_names().collect_vec();
+    let mut iter = with_givens
+      .find_solutions_with(SearchOptions::new().with_node_budget(20))
+      .with_names();
+    let mut solutions = iter.by_ref().collect_vec();
+    let checkpoint = SearchCheckpoint::from_bytes(&iter.checkpoint().to_bytes()).unwrap();
+    drop(iter);
+    drop(with_givens);
+
+    // The givens are part of the checkpoint, so resuming without them fails.
+    expect_that!(
+      dlx.resume_solutions(&checkpoint).err(),
+      some(eq(CheckpointError::GivensMismatch))
+    );
+    expect_that!(
+      dlx
+        .with_givens(&[9])
+        .unwrap()
+        .resume_solutions(&checkpoint)
+        .err(),
+      some(eq(CheckpointError::GivensMismatch))
+    );
+
+    let (mut rebuilt, givens) = Dlx::<u32, usize>::from_checkpoint(&checkpoint).unwrap();
+    expect_eq!(givens, vec![8]);
+    solutions.extend(
+      rebuilt
+        .with_givens(&givens)
+        .unwrap()
+        .resume_solutions(&checkpoint)
+        .unwrap()
+        .with_names(),
+    );
+    expect_eq!(solutions, expected);
+  }
+}
@@ -0,0 +1,349 @@
+use rand::Rng;
+
+use super::Dlx;
+
+/// An item which the search could branch on next, offered to an
+/// `ItemChooser`.
+#[derive(Debug)]
+pub struct ItemCandidate<'a, I> {
+  idx: u32,
+  item: &'a I,
+  branching_factor: usize,
+}
+
+impl<I> Clone for ItemCandidate<'_, I> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<I> Copy for ItemCandidate<'_, I> {}
+
+impl<'a, I> ItemCandidate<'a, I> {
+  pub(super) fn new(idx: u32, item: &'a I, branching_factor: usize) -> Self {
+    Self {
+      idx,
+      item,
+      branching_factor,
+    }
+  }
+
+  pub fn item(&self) -> &'a I {
+    self.item
+  }
+
+  /// The number of ways the search can branch on this item, which for items
+  /// without multiplicities is the number of subsets which could still cover
+  /// it.
+  pub fn branching_factor(&self) -> usize {
+    self.branching_factor
+  }
+
+  pub(super) fn idx(&self) -> u32 {
+    self.idx
+  }
+}
+
+/// Decides which primary item the search branches on at each node of the
+/// search tree. Every choice leads to the same solutions, but possibly in a
+/// different order, and with wildly different amounts of work.
+pub trait ItemChooser<I>: Send {
+  /// Chooses one of `candidates`, the primary items which still need to be
+  /// covered, in the order they were given to `Dlx::new`. Returning None
+  /// means there is nothing to choose, and is only allowed if `candidates` is
+  /// empty: the search panics otherwise.
+  fn choose<'a>(
+    &mut self,
+    candidates: &mut dyn Iterator<Item = ItemCandidate<'a, I>>,
+  ) -> Option<ItemCandidate<'a, I>>;
+}
+
+/// Chooses the item with the fewest remaining choices, preferring the
+/// earliest such item. This is the rule used when no chooser is given.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Mrv;
+
+impl<I> ItemChooser<I> for Mrv {
+  fn choose<'a>(
+    &mut self,
+    candidates: &mut dyn Iterator<Item = ItemCandidate<'a, I>>,
+  ) -> Option<ItemCandidate<'a, I>> {
+    candidates.min_by_key(|candidate| candidate.branching_factor)
+  }
+}
+
+/// Always chooses the first remaining item, like Knuth's programs do when
+/// the MRV heuristic is turned off.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FirstItem;
+
+impl<I> ItemChooser<I> for FirstItem {
+  fn choose<'a>(
+    &mut self,
+    candidates: &mut dyn Iterator<Item = ItemCandidate<'a, I>>,
+  ) -> Option<ItemCandidate<'a, I>> {
+    candidates.next()
+  }
+}
+
+/// Chooses an item with the fewest remaining choices, breaking ties
+/// uniformly at random.
+#[derive(Clone, Debug)]
+pub struct RandomizedMrv<R> {
+  rng: R,
+}
+
+impl<R> RandomizedMrv<R> {
+  pub fn new(rng: R) -> Self {
+    Self { rng }
+  }
+}
+
+impl<I, R> ItemChooser<I> for RandomizedMrv<R>
+where
+  R: Rng + Send,
+{
+  fn choose<'a>(
+    &mut self,
+    candidates: &mut dyn Iterator<Item = ItemCandidate<'a, I>>,
+  ) -> Option<ItemCandidate<'a, I>> {
+    let mut best: Option<ItemCandidate<'a, I>> = None;
+    let mut ties = 0;
+    for candidate in candidates {
+      match best {
+        Some(best_candidate) if best_candidate.branching_factor < candidate.branching_factor => {}
+        Some(best_candidate) if best_candidate.branching_factor == candidate.branching_factor => {
+          // Reservoir sampling: keep each of the tied candidates with equal
+          // probability.
+          ties += 1;
+          if self.rng.random_range(0..ties) == 0 {
+            best = Some(candidate);
+          }
+        }
+        _ => {
+          best = Some(candidate);
+          ties = 1;
+        }
+      }
+    }
+    best
+  }
+}
+
+/// Knuth's "sharp" preference: chooses the item with the fewest remaining
+/// choices among the items marked as preferred by `is_preferred`, and only
+/// considers the other items once every preferred item has been covered. In
+/// Knuth's programs, the preferred items are the ones whose names start with
+/// `#`.
+#[derive(Clone, Debug)]
+pub struct SharpPreference<F> {
+  is_preferred: F,
+}
+
+impl<F> SharpPreference<F> {
+  pub fn new(is_preferred: F) -> Self {
+    Self { is_preferred }
+  }
+}
+
+impl<I, F> ItemChooser<I> for SharpPreference<F>
+where
+  F: FnMut(&I) -> bool + Send,
+{
+  fn choose<'a>(
+    &mut self,
+    candidates: &mut dyn Iterator<Item = ItemCandidate<'a, I>>,
+  ) -> Option<ItemCandidate<'a, I>> {
+    candidates.min_by_key(|candidate| {
+      (
+        !(self.is_preferred)(candidate.item),
+        candidate.branching_factor,
+      )
+    })
+  }
+}
+
+impl<I, N> Dlx<I, N> {
+  /// Iterates over the primary items which still need to be covered.
+  pub(super) fn item_candidates(&self) -> impl Iterator<Item = ItemCandidate<'_, I>> + '_ {
+    let mut idx = self.header(0).node.next;
+    std::iter::from_fn(move || {
+      if idx == 0 {
+        return None;
+      }
+      let candidate = ItemCandidate {
+        idx,
+        item: self.header(idx as usize).item.as_ref().unwrap(),
+        branching_factor: self.branching_factor(idx as usize),
+      };
+      idx = self.header(idx as usize).node.next;
+      Some(candidate)
+    })
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::sync::{Arc, Mutex};
+
+  use googletest::prelude::*;
+  use itertools::Itertools;
+  use rand::{rngs::StdRng, SeedableRng};
+
+  use crate::{
+    dlx::test::random_problem, Dlx, DlxIterator, DlxIteratorWithNames, FirstItem, HeaderType,
+    ItemCandidate, ItemChooser, Mrv, RandomizedMrv, SearchOptions, SharpPreference,
+    StepwiseDlxIterResult,
+  };
+
+  fn sorted_solutions<I>(solutions: impl DlxIterator<I, u32>) -> Vec<Vec<u32>> {
+    solutions
+      .with_names()
+      .map(|solution| solution.into_iter().sorted().collect_vec())
+      .sorted()
+      .collect()
+  }
+
+  #[gtest]
+  fn test_choosers_find_same_solutions() {
+    for seed in 0..50 {
+      let (items, subsets) = random_problem(seed);
+      let mut dlx = Dlx::new(items, subsets);
+      let expected = sorted_solutions(dlx.find_solutions());
+
+      expect_eq!(
+        sorted_solutions(dlx.find_solutions_with(SearchOptions::new().with_chooser(Mrv))),
+        expected,
+        "seed {seed}"
+      );
+      expect_eq!(
+        sorted_solutions(dlx.find_solutions_with(SearchOptions::new().with_chooser(FirstItem))),
+        expected,
+        "seed {seed}"
+      );
+      expect_eq!(
+        sorted_solutions(dlx.find_solutions_with(
+          SearchOptions::new().with_chooser(RandomizedMrv::new(StdRng::seed_from_u64(seed)))
+        )),
+        expected,
+        "seed {seed}"
+      );
+      expect_eq!(
+        sorted_solutions(dlx.find_solutions_with(
+          SearchOptions::new().with_chooser(SharpPreference::new(|item: &u32| item % 2 == 1))
+        )),
+        expected,
+        "seed {seed}"
+      );
+    }
+  }
+
+  #[gtest]
+  fn test_mrv_matches_default_search() {
+    for seed in 0..50 {
+      let (items, subsets) = random_problem(seed);
+      let mut dlx = Dlx::new(items, subsets);
+      let expected = dlx
+        .find_solutions_stepwise()
+        .map(StepwiseDlxIterResult::take_result)
+        .collect_vec();
+      expect_eq!(
+        dlx
+          .find_solutions_stepwise_with(SearchOptions::new().with_chooser(Mrv))
+          .map(StepwiseDlxIterResult::take_result)
+          .collect_vec(),
+        expected,
+        "seed {seed}"
+      );
+    }
+  }
+
+  /// Records the items chosen by the wrapped chooser.
+  struct Recording<C> {
+    chooser: C,
+    chosen: Arc<Mutex<Vec<char>>>,
+  }
+
+  impl<C> ItemChooser<char> for Recording<C>
+  where
+    C: ItemChooser<char>,
+  {
+    fn choose<'a>(
+      &mut self,
+      candidates: &mut dyn Iterator<Item = ItemCandidate<'a, char>>,
+    ) -> Option<ItemCandidate<'a, char>> {
+      let choice = self.chooser.choose(candidates);
+      if let Some(choice) = choice {
+        self.chosen.lock().unwrap().push(*choice.item());
+      }
+      choice
+    }
+  }
+
+  /// Returns the items chosen by `chooser` on the way to the first solution.
+  fn chosen_items(chooser: impl ItemChooser<char> + 'static) -> Vec<char> {
+    // `p` has the fewest subsets, `q` is first, and `#` is preferred.
+    let mut dlx = Dlx::new(
+      vec![
+        ('q', HeaderType::Primary),
+        ('p', HeaderType::Primary),
+        ('#', HeaderType::Primary),
+      ],
+      vec![
+        (0, vec!['p', 'q']),
+        (1, vec!['q']),
+        (2, vec!['#']),
+        (3, vec!['#', 'q']),
+        (4, vec!['#']),
+      ],
+    );
+    let chosen = Arc::new(Mutex::new(Vec::new()));
+    let recording = Recording {
+      chooser,
+      chosen: chosen.clone(),
+    };
+    expect_that!(
+      dlx
+        .find_solutions_with(SearchOptions::new().with_chooser(recording))
+        .next(),
+      some(len(eq(2)))
+    );
+    let chosen = chosen.lock().unwrap().clone();
+    chosen
+  }
+
+  /// Gives up as soon as it has to choose between several items.
+  struct GivesUp;
+
+  impl ItemChooser<char> for GivesUp {
+    fn choose<'a>(
+      &mut self,
+      candidates: &mut dyn Iterator<Item = ItemCandidate<'a, char>>,
+    ) -> Option<ItemCandidate<'a, char>> {
+      let first = candidates.next();
+      candidates.next().is_none().then_some(first).flatten()
+    }
+  }
+
+  #[test]
+  #[should_panic(expected = "ItemChooser returned None while primary items remain uncovered")]
+  fn test_chooser_must_choose_an_item() {
+    let mut dlx = Dlx::new(
+      vec![('p', HeaderType::Primary), ('q', HeaderType::Primary)],
+      vec![(0, vec!['p']), (1, vec!['q'])],
+    );
+    dlx
+      .find_solutions_with(SearchOptions::new().with_chooser(GivesUp))
+      .next();
+  }
+
+  #[gtest]
+  fn test_chooser_order() {
+    expect_that!(chosen_items(Mrv), elements_are![&'p', &'#']);
+    expect_that!(chosen_items(FirstItem), elements_are![&'q', &'#']);
+    expect_that!(
+      chosen_items(SharpPreference::new(|item: &char| *item == '#')),
+      elements_are![&'#', &'p']
+    );
+  }
+}
@@ -0,0 +1,804 @@
+use std::{
+  error::Error,
+  fmt::{self, Debug, Display, Formatter},
+  hash::Hash,
+  ops::Range,
+};
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use super::{
+  Constraint, Dlx, DlxError, HeaderType, ItemCandidate, Node, NodeType, RandomizedMrv,
+  SearchOptions, SearchStats, SearchStatus,
+};
+
+/// The ways building a `DancingCells` can fail.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DancingCellsError<I, N> {
+  /// The input is malformed, as reported by `Dlx::try_new`.
+  Dlx(DlxError<I, N>),
+  /// A primary item has multiplicities, i.e. may or must be covered more
+  /// than once, which dancing cells doesn't support.
+  UnsupportedMultiplicity(I),
+}
+
+impl<I, N> From<DlxError<I, N>> for DancingCellsError<I, N> {
+  fn from(err: DlxError<I, N>) -> Self {
+    DancingCellsError::Dlx(err)
+  }
+}
+
+impl<I, N> Display for DancingCellsError<I, N>
+where
+  I: Debug,
+  N: Debug,
+{
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      DancingCellsError::Dlx(err) => write!(f, "{err}"),
+      DancingCellsError::UnsupportedMultiplicity(item) => {
+        write!(
+          f,
+          "Item {item:?} has multiplicities, which aren't supported"
+        )
+      }
+    }
+  }
+}
+
+impl<I, N> Error for DancingCellsError<I, N>
+where
+  I: Debug,
+  N: Debug,
+{
+}
+
+/// A set of items which can remove items in O(1), and restore everything
+/// removed since some point by resetting its length, since removed items are
+/// only ever swapped past the end of the active prefix.
+#[derive(Clone, Debug)]
+struct SparseSet {
+  items: Vec<usize>,
+  /// The position of each item in `items`, indexed by item.
+  pos: Vec<usize>,
+  len: usize,
+}
+
+impl SparseSet {
+  fn new(items: impl Iterator<Item = usize>, num_items: usize) -> Self {
+    let items = items.collect::<Vec<_>>();
+    let mut pos = vec![usize::MAX; num_items];
+    for (idx, &item) in items.iter().enumerate() {
+      pos[item] = idx;
+    }
+    Self {
+      len: items.len(),
+      items,
+      pos,
+    }
+  }
+
+  fn active(&self) -> &[usize] {
+    &self.items[..self.len]
+  }
+
+  fn contains(&self, item: usize) -> bool {
+    self.pos[item] < self.len
+  }
+
+  fn remove(&mut self, item: usize) {
+    let idx = self.pos[item];
+    let last = self.items[self.len - 1];
+    self.items.swap(idx, self.len - 1);
+    self.pos[last] = idx;
+    self.pos[item] = self.len - 1;
+    self.len -= 1;
+  }
+}
+
+/// One entry in the option table: an item of an option.
+#[derive(Clone, Copy, Debug)]
+struct Cell {
+  item: usize,
+  color: Option<u32>,
+  option: usize,
+}
+
+/// An exact cover solver using Knuth's "dancing cells": instead of doubly
+/// linked lists, the options of each item and the active items are kept in
+/// sparse sets, whose removals are undone simply by restoring their sizes.
+/// This trades the pointer chasing of dancing links for sequential memory
+/// access, which is often faster on large problems.
+///
+/// Built from the same input as `Dlx::new`, and finds the same solutions,
+/// though possibly in a different order. Items with multiplicities aren't
+/// supported. Searches take the same `SearchOptions` as `Dlx` searches. Use
+/// `ExactCoverSolver` to write code which works with either.
+#[derive(Clone, Debug)]
+pub struct DancingCells<I, N> {
+  /// The name of each item.
+  items: Vec<I>,
+  names: Vec<N>,
+  cells: Vec<Cell>,
+  /// The cells of option `o` are `option_start[o]..option_start[o + 1]`.
+  option_start: Vec<usize>,
+  /// The cells of each item, where `set[start[i]..(start[i] + size[i])]`
+  /// are the cells of item `i` whose options are still available.
+  set: Vec<usize>,
+  /// The position of each cell in `set`.
+  loc: Vec<usize>,
+  start: Vec<usize>,
+  size: Vec<usize>,
+  /// The primary items which haven't been covered.
+  primary: SparseSet,
+  /// The secondary items which haven't been covered or colored.
+  secondary: SparseSet,
+  /// The number of cells removed from the sets of active items by `hide`,
+  /// for `SearchStats`. Only counted while `count_updates` is set.
+  updates: u64,
+  count_updates: bool,
+}
+
+impl<I, N> DancingCells<I, N>
+where
+  I: Hash + Eq + Clone + Debug,
+  N: Hash + Eq + Clone + Debug,
+{
+  /// Like `Dlx::new`, panicking if the input is malformed.
+  pub fn new<U, S, C, D>(items: U, subsets: S) -> Self
+  where
+    U: IntoIterator<Item = (I, HeaderType)>,
+    S: IntoIterator<Item = (N, C)>,
+    C: IntoIterator<Item = D>,
+    D: Into<Constraint<I>>,
+  {
+    Self::try_new(items, subsets).unwrap_or_else(|err| panic!("{err}"))
+  }
+
+  /// Like `Dlx::try_new`, but also fails with
+  /// `DancingCellsError::UnsupportedMultiplicity` if an item has
+  /// multiplicities.
+  pub fn try_new<U, S, C, D>(items: U, subsets: S) -> Result<Self, DancingCellsError<I, N>>
+  where
+    U: IntoIterator<Item = (I, HeaderType)>,
+    S: IntoIterator<Item = (N, C)>,
+    C: IntoIterator<Item = D>,
+    D: Into<Constraint<I>>,
+  {
+    Self::from_dlx(&Dlx::try_new(items, subsets)?)
+  }
+}
+
+impl<I, N> DancingCells<I, N>
+where
+  I: Clone,
+  N: Clone,
+{
+  /// Builds the same problem as `dlx`, which must not be in the middle of a
+  /// search.
+  pub fn from_dlx(dlx: &Dlx<I, N>) -> Result<Self, DancingCellsError<I, N>> {
+    let num_items = dlx.headers.len() - 2;
+    for header in &dlx.headers[1..=num_items] {
+      if let HeaderType::BoundedPrimary { min, max } = header.header_type {
+        if (min, max) != (1, 1) {
+          return Err(DancingCellsError::UnsupportedMultiplicity(
+            header.item.clone().unwrap(),
+          ));
+        }
+      }
+    }
+
+    let mut names = Vec::new();
+    let mut cells = Vec::new();
+    let mut option_start = vec![0];
+    for idx in dlx.headers.len()..dlx.body.len() {
+      match dlx.body_node(idx) {
+        Node::Boundary { name, .. } => {
+          names.push(name.clone().unwrap());
+          option_start.push(cells.len());
+        }
+        Node::Normal {
+          node_type: NodeType::Body { top, color, .. },
+          ..
+        } => cells.push(Cell {
+          item: *top as usize - 1,
+          color: *color,
+          option: names.len(),
+        }),
+        Node::Normal {
+          node_type: NodeType::Header { .. },
+          ..
+        } => dlx_unreachable!("Unexpected header in body"),
+      }
+    }
+
+    let mut size = vec![0; num_items];
+    for cell in &cells {
+      size[cell.item] += 1;
+    }
+    let start = size
+      .iter()
+      .scan(0, |next, &size| {
+        let start = *next;
+        *next += size;
+        Some(start)
+      })
+      .collect::<Vec<_>>();
+    let mut set = vec![0; cells.len()];
+    let mut loc = vec![0; cells.len()];
+    let mut filled = vec![0; num_items];
+    for (idx, cell) in cells.iter().enumerate() {
+      let pos = start[cell.item] + filled[cell.item];
+      filled[cell.item] += 1;
+      set[pos] = idx;
+      loc[idx] = pos;
+    }
+
+    let num_primary = dlx.num_primary_items;
+    Ok(Self {
+      items: dlx.headers[1..=num_items]
+        .iter()
+        .map(|header| header.item.clone().unwrap())
+        .collect(),
+      names,
+      cells,
+      option_start,
+      set,
+      loc,
+      start,
+      size,
+      primary: SparseSet::new(0..num_primary, num_items),
+      secondary: SparseSet::new(num_primary..num_items, num_items),
+      updates: 0,
+      count_updates: false,
+    })
+  }
+}
+
+impl<I, N> DancingCells<I, N> {
+  /// Iterates over the items of this problem, like `Dlx::items`.
+  pub fn items(&self) -> impl Iterator<Item = (&I, HeaderType)> + '_ {
+    let num_primary = self.primary.items.len();
+    self.items.iter().enumerate().map(move |(idx, item)| {
+      let header_type = if idx < num_primary {
+        HeaderType::Primary
+      } else {
+        HeaderType::Secondary
+      };
+      (item, header_type)
+    })
+  }
+
+  fn is_active(&self, item: usize) -> bool {
+    self.primary.contains(item) || self.secondary.contains(item)
+  }
+
+  /// The cells of the option containing `cell`.
+  fn option_cells(&self, cell: usize) -> Range<usize> {
+    let option = self.cells[cell].option;
+    self.option_start[option]..self.option_start[option + 1]
+  }
+
+  /// Removes the option containing `cell` from the sets of all its other
+  /// active items.
+  fn hide(&mut self, cell: usize) {
+    for other in self.option_cells(cell) {
+      let item = self.cells[other].item;
+      if other == cell || !self.is_active(item) {
+        continue;
+      }
+      debug_assert!(self.loc[other] < self.start[item] + self.size[item]);
+      let last = self.start[item] + self.size[item] - 1;
+      let moved = self.set[last];
+      self.set.swap(self.loc[other], last);
+      self.loc[moved] = self.loc[other];
+      self.loc[other] = last;
+      self.size[item] -= 1;
+      if self.count_updates {
+        self.updates += 1;
+      }
+    }
+  }
+
+  /// Deactivates `item`, hiding every option which has it, or if `color` is
+  /// given, every option which gives it a different color.
+  fn cover(&mut self, item: usize, color: Option<u32>) {
+    if self.primary.contains(item) {
+      self.primary.remove(item);
+    } else {
+      self.secondary.remove(item);
+    }
+    for pos in self.start[item]..(self.start[item] + self.size[item]) {
+      let cell = self.set[pos];
+      if color.is_none() || self.cells[cell].color != color {
+        self.hide(cell);
+      }
+    }
+  }
+
+  /// Covers the remaining items of the option containing `cell`, which was
+  /// chosen for its item.
+  fn select(&mut self, cell: usize) {
+    for other in self.option_cells(cell) {
+      let Cell { item, color, .. } = self.cells[other];
+      // A secondary item which is no longer active has already been given
+      // the same color by an earlier option.
+      if other != cell && self.is_active(item) {
+        self.cover(item, color);
+      }
+    }
+  }
+
+  /// Iterates over the primary items which still need to be covered, in the
+  /// order they were given in.
+  fn item_candidates(&self) -> impl Iterator<Item = ItemCandidate<'_, I>> + '_ {
+    (0..self.primary.items.len())
+      .filter(|&item| self.primary.contains(item))
+      .map(|item| ItemCandidate::new(item as u32, &self.items[item], self.size[item]))
+  }
+
+  /// Returns the active primary item with the fewest remaining options, or
+  /// None if every primary item has been covered.
+  fn choose_item(&self) -> Option<usize> {
+    self
+      .primary
+      .active()
+      .iter()
+      .copied()
+      .min_by_key(|&item| self.size[item])
+  }
+
+  /// Records the lengths of the active sets and the sizes of their items.
+  fn save(&self, saved: &mut Vec<(usize, usize)>) {
+    saved.push((self.primary.len, self.secondary.len));
+    for &item in self.primary.active().iter().chain(self.secondary.active()) {
+      saved.push((item, self.size[item]));
+    }
+  }
+
+  /// Reverts everything done since `save` recorded `saved`.
+  fn restore(&mut self, saved: &[(usize, usize)]) {
+    let (primary_len, secondary_len) = saved[0];
+    self.primary.len = primary_len;
+    self.secondary.len = secondary_len;
+    for &(item, size) in &saved[1..] {
+      self.size[item] = size;
+    }
+  }
+
+  /// Shuffles the order in which the options of each primary item are
+  /// tried. The options are sorted first, so that the order only depends on
+  /// `rng`, and not on the searches run before.
+  fn shuffle_options<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+    for item in 0..self.primary.items.len() {
+      let cells = &mut self.set[self.start[item]..(self.start[item] + self.size[item])];
+      cells.sort_unstable();
+      cells.shuffle(rng);
+      for (pos, &cell) in (self.start[item]..).zip(cells.iter()) {
+        self.loc[cell] = pos;
+      }
+    }
+  }
+
+  /// Iterates over the solutions, as the names of the subsets in each.
+  pub fn find_solutions(&mut self) -> DancingCellsSolutions<'_, I, N> {
+    self.find_solutions_with(SearchOptions::default())
+  }
+
+  /// Like `find_solutions`, but searching with `options`, which work the
+  /// same as in `Dlx::find_solutions_with`. The same seed gives the same
+  /// search, but not the same one as in `Dlx`.
+  pub fn find_solutions_with(
+    &mut self,
+    mut options: SearchOptions<I>,
+  ) -> DancingCellsSolutions<'_, I, N> {
+    if let Some(seed) = options.seed {
+      let mut rng = StdRng::seed_from_u64(seed);
+      self.shuffle_options(&mut rng);
+      options
+        .chooser
+        .get_or_insert_with(|| Box::new(RandomizedMrv::new(StdRng::from_rng(&mut rng))));
+    }
+    let prev_count_updates = self.count_updates;
+    if options.collect_stats {
+      self.count_updates = true;
+    }
+    let mut saved = Vec::new();
+    self.save(&mut saved);
+    DancingCellsSolutions {
+      initial_updates: self.updates,
+      prev_count_updates,
+      dc: self,
+      levels: Vec::new(),
+      initial_saved: saved.len(),
+      saved,
+      state: SearchState::NotStarted,
+      stats: options.collect_stats.then(SearchStats::default),
+      options,
+      nodes: 0,
+      status: SearchStatus::Running,
+    }
+  }
+
+  /// Counts the solutions, without materializing any of them.
+  pub fn count_solutions(&mut self) -> u64 {
+    let mut solutions = self.find_solutions();
+    let mut count = 0;
+    while solutions.advance() {
+      count += 1;
+    }
+    count
+  }
+}
+
+/// A level of the search in `DancingCellsSolutions`.
+#[derive(Debug)]
+struct Level {
+  /// The item being branched on, which is inactive, so its set stays in the
+  /// same order while the level is on the stack.
+  item: usize,
+  /// The number of options of `item` tried so far.
+  tried: usize,
+  /// Where the state right after covering `item` starts in `saved`.
+  saved: usize,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum SearchState {
+  NotStarted,
+  /// A solution was just returned, so the search should backtrack.
+  FoundSolution,
+  Done,
+}
+
+/// Iterates over the solutions found by `DancingCells::find_solutions`. The
+/// problem is restored when the iterator is dropped.
+#[derive(Debug)]
+pub struct DancingCellsSolutions<'a, I, N> {
+  dc: &'a mut DancingCells<I, N>,
+  levels: Vec<Level>,
+  /// The states saved for each level, after the state before the search.
+  saved: Vec<(usize, usize)>,
+  /// The length of the state before the search at the start of `saved`.
+  initial_saved: usize,
+  state: SearchState,
+  options: SearchOptions<I>,
+  /// The number of nodes of the search tree visited so far.
+  nodes: u64,
+  status: SearchStatus,
+  stats: Option<SearchStats>,
+  /// The value of `DancingCells::updates` when the search started.
+  initial_updates: u64,
+  /// The value of `DancingCells::count_updates` before the search started,
+  /// which is put back when it ends.
+  prev_count_updates: bool,
+}
+
+impl<I, N> DancingCellsSolutions<'_, I, N> {
+  /// Reports whether the search is still running, or why it stopped, like
+  /// `DlxIterator::status`.
+  pub fn status(&self) -> SearchStatus {
+    self.status
+  }
+
+  /// Returns the statistics collected so far, if the search was started with
+  /// `SearchOptions::with_stats`. Updates count the cells removed from the
+  /// sets of active items, since restoring them is free.
+  pub fn stats(&self) -> Option<&SearchStats> {
+    self.stats.as_ref()
+  }
+
+  /// Chooses the primary item to branch on at the current node, or None if
+  /// every primary item has been covered.
+  fn choose_item(&mut self) -> Option<usize> {
+    let dc = &*self.dc;
+    let item = match &mut self.options.chooser {
+      Some(chooser) => chooser
+        .choose(&mut dc.item_candidates())
+        .map(|candidate| candidate.idx() as usize),
+      None => dc.choose_item(),
+    };
+    // A chooser giving up early would make the search report a partial cover
+    // as a solution.
+    assert!(
+      item.is_some() || dc.primary.len == 0,
+      "ItemChooser returned None while primary items remain uncovered"
+    );
+
+    if let Some(stats) = &mut self.stats {
+      let depth = self.levels.len();
+      stats.record_node(depth);
+      match item {
+        Some(item) => stats.record_branch(depth, dc.size[item]),
+        None => stats.record_solution(),
+      }
+      stats.set_updates(dc.updates.wrapping_sub(self.initial_updates));
+    }
+    item
+  }
+
+  /// Tries the next option of the deepest level, backtracking as needed.
+  /// Returns false once every level has run out of options.
+  fn try_next_option(&mut self) -> bool {
+    while let Some(level) = self.levels.last_mut() {
+      self.dc.restore(&self.saved[level.saved..]);
+      if level.tried == self.dc.size[level.item] {
+        self.saved.truncate(level.saved);
+        self.levels.pop();
+        continue;
+      }
+      let cell = self.dc.set[self.dc.start[level.item] + level.tried];
+      level.tried += 1;
+      self.dc.select(cell);
+      return true;
+    }
+    false
+  }
+
+  /// Runs the search until the next solution, returning false once there are
+  /// no more, or the search hit one of the limits in its options.
+  fn advance(&mut self) -> bool {
+    if self.status != SearchStatus::Running {
+      return false;
+    }
+    match self.state {
+      SearchState::NotStarted => {}
+      SearchState::FoundSolution => {
+        if !self.try_next_option() {
+          self.finish();
+          return false;
+        }
+      }
+      SearchState::Done => return false,
+    }
+
+    loop {
+      if let Some(status) = self.options.exceeded_limit(self.nodes) {
+        // Leave the levels in place, the `Drop` impl will restore the problem.
+        self.status = status;
+        return false;
+      }
+      self.nodes += 1;
+
+      let Some(item) = self.choose_item() else {
+        self.state = SearchState::FoundSolution;
+        return true;
+      };
+      self.dc.cover(item, None);
+      let saved = self.saved.len();
+      self.dc.save(&mut self.saved);
+      self.levels.push(Level {
+        item,
+        tried: 0,
+        saved,
+      });
+      if !self.try_next_option() {
+        self.finish();
+        return false;
+      }
+    }
+  }
+
+  fn finish(&mut self) {
+    self.levels.clear();
+    self.saved.truncate(self.initial_saved);
+    self.dc.restore(&self.saved);
+    self.state = SearchState::Done;
+    if self.status == SearchStatus::Running {
+      self.status = SearchStatus::Exhausted;
+    }
+  }
+
+  /// The options chosen at each level of the current solution.
+  fn solution(&self) -> impl Iterator<Item = usize> + '_ {
+    self.levels.iter().map(|level| {
+      let cell = self.dc.set[self.dc.start[level.item] + level.tried - 1];
+      self.dc.cells[cell].option
+    })
+  }
+}
+
+impl<I, N> Iterator for DancingCellsSolutions<'_, I, N>
+where
+  N: Clone,
+{
+  type Item = Vec<N>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    self.advance().then(|| {
+      self
+        .solution()
+        .map(|option| self.dc.names[option].clone())
+        .collect()
+    })
+  }
+}
+
+impl<I, N> Drop for DancingCellsSolutions<'_, I, N> {
+  fn drop(&mut self) {
+    if self.state != SearchState::Done {
+      self.finish();
+    }
+    self.dc.count_updates = self.prev_count_updates;
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use googletest::prelude::*;
+  use itertools::Itertools;
+
+  use crate::{
+    dlx::test::{random_problem, singletons_and_pairs},
+    CancellationToken, DancingCells, DancingCellsError, Dlx, DlxError, DlxIteratorWithNames,
+    ExactCoverSolver, FirstItem, HeaderType, SearchOptions, SearchStatus, SolverIterator,
+  };
+
+  fn sorted_solutions<N: Ord>(solutions: impl Iterator<Item = Vec<N>>) -> Vec<Vec<N>> {
+    solutions
+      .map(|solution| solution.into_iter().sorted().collect_vec())
+      .sorted()
+      .collect()
+  }
+
+  #[gtest]
+  fn test_dancing_cells_matches_dlx() {
+    for seed in 0..200 {
+      // Dancing cells doesn't support multiplicities, so make every bounded
+      // item plain primary.
+      let (items, subsets) = random_problem(seed);
+      let items = items
+        .into_iter()
+        .map(|(item, header_type)| match header_type {
+          HeaderType::BoundedPrimary { .. } => (item, HeaderType::Primary),
+          _ => (item, header_type),
+        })
+        .collect_vec();
+      let mut dlx = Dlx::new(items.clone(), subsets.clone());
+      let mut dc = DancingCells::new(items, subsets);
+
+      let expected = sorted_solutions(dlx.find_solutions().with_names());
+      expect_eq!(
+        sorted_solutions(dc.find_solutions()),
+        expected,
+        "seed {seed}"
+      );
+      expect_eq!(dc.count_solutions(), expected.len() as u64, "seed {seed}");
+    }
+  }
+
+  #[gtest]
+  fn test_dancing_cells_restores_after_early_stop() {
+    let dlx = singletons_and_pairs();
+    let mut dc = DancingCells::from_dlx(&dlx).unwrap();
+
+    expect_eq!(dc.find_solutions().take(10).count(), 10);
+    expect_eq!(dc.count_solutions(), 764);
+    expect_eq!(dc.find_solutions().count(), 764);
+  }
+
+  #[gtest]
+  fn test_dancing_cells_rejects_multiplicities() {
+    expect_that!(
+      DancingCells::try_new(
+        vec![('p', HeaderType::BoundedPrimary { min: 0, max: 2 })],
+        vec![(0, vec!['p'])],
+      )
+      .err(),
+      some(eq(&DancingCellsError::UnsupportedMultiplicity('p')))
+    );
+    // Bounds of exactly one are the same as a plain primary item.
+    expect_that!(
+      DancingCells::try_new(
+        vec![('p', HeaderType::BoundedPrimary { min: 1, max: 1 })],
+        vec![(0, vec!['p'])],
+      )
+      .map(|mut dc| dc.count_solutions()),
+      ok(eq(&1))
+    );
+  }
+
+  #[gtest]
+  fn test_dancing_cells_items() {
+    let dc = DancingCells::new(
+      vec![('a', HeaderType::Secondary), ('p', HeaderType::Primary)],
+      vec![(0, vec!['p'])],
+    );
+    expect_that!(
+      dc.items().collect_vec(),
+      elements_are![
+        eq(&(&'p', HeaderType::Primary)),
+        eq(&(&'a', HeaderType::Secondary))
+      ]
+    );
+  }
+
+  #[gtest]
+  fn test_dancing_cells_rejects_malformed_input() {
+    expect_that!(
+      DancingCells::<_, u32>::try_new(vec![('p', HeaderType::Primary)], vec![(0, vec!['q'])]).err(),
+      some(eq(&DancingCellsError::Dlx(DlxError::UnknownItem {
+        subset: 0,
+        item: 'q'
+      })))
+    );
+  }
+
+  #[gtest]
+  fn test_dancing_cells_search_options() {
+    let dlx = singletons_and_pairs();
+    let mut dc = DancingCells::from_dlx(&dlx).unwrap();
+
+    let mut solutions = dc.find_solutions_with(SearchOptions::new().with_node_budget(100));
+    let found = solutions.by_ref().count();
+    expect_that!(found, gt(0).and(lt(764)));
+    expect_eq!(solutions.status(), SearchStatus::NodeBudgetExceeded);
+    drop(solutions);
+
+    let token = CancellationToken::new();
+    token.cancel();
+    let mut solutions = dc.find_solutions_with(SearchOptions::new().with_cancellation(token));
+    expect_that!(solutions.next(), none());
+    expect_eq!(solutions.status(), SearchStatus::Cancelled);
+    drop(solutions);
+
+    let mut solutions = dc.find_solutions_with(SearchOptions::new().with_stats());
+    expect_eq!(solutions.by_ref().count(), 764);
+    expect_eq!(solutions.status(), SearchStatus::Exhausted);
+    let stats = solutions.stats().unwrap().clone();
+    drop(solutions);
+    expect_eq!(stats.solutions(), 764);
+    expect_that!(stats.nodes(), gt(764));
+    expect_that!(stats.updates(), gt(0));
+    expect_eq!(stats.max_depth(), 8);
+    expect_eq!(dc.count_solutions(), 764);
+  }
+
+  #[gtest]
+  fn test_dancing_cells_seed_and_chooser() {
+    let dlx = singletons_and_pairs();
+    let mut dc = DancingCells::from_dlx(&dlx).unwrap();
+    let expected = sorted_solutions(dc.find_solutions());
+
+    let seeded = |dc: &mut DancingCells<u32, usize>, seed| {
+      dc.find_solutions_with(SearchOptions::new().with_seed(seed))
+        .take(20)
+        .collect_vec()
+    };
+    let first = seeded(&mut dc, 1);
+    expect_eq!(seeded(&mut dc, 1), first);
+    expect_ne!(seeded(&mut dc, 2), first);
+    expect_eq!(
+      sorted_solutions(dc.find_solutions_with(SearchOptions::new().with_seed(1))),
+      expected
+    );
+    expect_eq!(
+      sorted_solutions(dc.find_solutions_with(SearchOptions::new().with_chooser(FirstItem))),
+      expected
+    );
+  }
+
+  /// Solves the problem with any backend.
+  fn count_with<S: ExactCoverSolver<u32, usize>>() -> (u64, usize, u64) {
+    let mut solver = S::try_new(
+      (0..6).map(|item| (item, HeaderType::Primary)),
+      (0..6)
+        .map(|item| vec![item])
+        .chain((0..6).tuple_combinations().map(|(a, b)| vec![a, b]))
+        .enumerate(),
+    )
+    .unwrap();
+    let mut solutions = solver.solutions_with(SearchOptions::new().with_stats());
+    let count = solutions.by_ref().count();
+    expect_eq!(solutions.status(), SearchStatus::Exhausted);
+    let stats_solutions = solutions.stats().unwrap().solutions();
+    drop(solutions);
+    (solver.count_solutions(), count, stats_solutions)
+  }
+
+  #[gtest]
+  fn test_solver_backends_agree() {
+    expect_eq!(count_with::<Dlx<_, _>>(), (76, 76, 76));
+    expect_eq!(count_with::<DancingCells<_, _>>(), (76, 76, 76));
+  }
+}
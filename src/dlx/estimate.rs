@@ -0,0 +1,261 @@
+use rand::Rng;
+
+use super::{ChooseNextItemResult, Dlx, DlxExplorer, ExploreNextChoiceResult};
+
+/// An estimate of the size of a search, from `Dlx::estimate_search`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchEstimate {
+  probes: usize,
+  nodes: f64,
+  nodes_variance: f64,
+  updates: f64,
+  updates_variance: f64,
+  solutions: f64,
+  solutions_variance: f64,
+}
+
+/// Returns the mean and sample variance of `values`.
+fn mean_and_variance(values: &[f64]) -> (f64, f64) {
+  if values.is_empty() {
+    return (0., 0.);
+  }
+  let n = values.len() as f64;
+  let mean = values.iter().sum::<f64>() / n;
+  let variance = if values.len() < 2 {
+    0.
+  } else {
+    values
+      .iter()
+      .map(|value| (value - mean).powi(2))
+      .sum::<f64>()
+      / (n - 1.)
+  };
+  (mean, variance)
+}
+
+/// The estimates made by a single probe.
+#[derive(Clone, Copy, Debug)]
+struct Probe {
+  nodes: f64,
+  updates: f64,
+  solutions: f64,
+}
+
+impl SearchEstimate {
+  fn new(probes: &[Probe]) -> Self {
+    let estimates =
+      |f: fn(&Probe) -> f64| mean_and_variance(&probes.iter().map(f).collect::<Vec<_>>());
+    let (nodes, nodes_variance) = estimates(|probe| probe.nodes);
+    let (updates, updates_variance) = estimates(|probe| probe.updates);
+    let (solutions, solutions_variance) = estimates(|probe| probe.solutions);
+    Self {
+      probes: probes.len(),
+      nodes,
+      nodes_variance,
+      updates,
+      updates_variance,
+      solutions,
+      solutions_variance,
+    }
+  }
+
+  /// The number of random probes the estimate was made from.
+  pub fn probes(&self) -> usize {
+    self.probes
+  }
+
+  /// The estimated number of nodes in the search tree, which is the number of
+  /// steps `find_solutions_stepwise` would take.
+  pub fn nodes(&self) -> f64 {
+    self.nodes
+  }
+
+  /// The sample variance of the node counts estimated by individual probes.
+  /// The variance of `nodes()` itself is this divided by the number of
+  /// probes.
+  pub fn nodes_variance(&self) -> f64 {
+    self.nodes_variance
+  }
+
+  /// The estimated number of links the search would remove and restore, as
+  /// counted by `SearchStats::updates`. This tracks the running time of a
+  /// search more closely than `nodes()`, since nodes high in the tree cost
+  /// far more than nodes near the leaves.
+  pub fn updates(&self) -> f64 {
+    self.updates
+  }
+
+  /// The sample variance of the update counts estimated by individual
+  /// probes.
+  pub fn updates_variance(&self) -> f64 {
+    self.updates_variance
+  }
+
+  /// The estimated number of solutions.
+  pub fn solutions(&self) -> f64 {
+    self.solutions
+  }
+
+  /// The sample variance of the solution counts estimated by individual
+  /// probes.
+  pub fn solutions_variance(&self) -> f64 {
+    self.solutions_variance
+  }
+}
+
+impl<I, N> Dlx<I, N> {
+  /// Follows a single random path from the root of the search tree to a
+  /// leaf, returning unbiased estimates of the number of nodes and solutions
+  /// in the tree, and an estimate of the number of updates.
+  fn probe<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Probe {
+    let count_updates = self.count_updates;
+    self.count_updates = true;
+    let mut explorer = DlxExplorer::new(&mut *self);
+    // The number of nodes at the current depth, assuming every node at each
+    // depth above had as many children as the one on this path.
+    let mut weight = 1.;
+    let mut nodes = 0.;
+    let mut updates = 0.;
+    let updates_since = |explorer: &DlxExplorer<_, I, N>, start: u64| {
+      explorer.dlx().updates.wrapping_sub(start) as f64
+    };
+    let probe = loop {
+      nodes += weight;
+
+      let depth = explorer.partial_solution().len();
+      let dlx = explorer.dlx();
+      let children = dlx
+        .choose_item()
+        .map_or(0, |item| dlx.branching_factor(item as usize));
+      let start = dlx.updates;
+      if let ChooseNextItemResult::FoundSolution = explorer.choose_next_item() {
+        break Probe {
+          nodes,
+          updates,
+          solutions: weight,
+        };
+      }
+      // Entering the item costs as many updates as leaving it again.
+      let entered = 2. * updates_since(&explorer, start);
+      if children == 0 {
+        updates += weight * entered;
+        break Probe {
+          nodes,
+          updates,
+          solutions: 0.,
+        };
+      }
+
+      // Moving on to a random child costs about as much as any other move
+      // between children, which the full search makes once for each child,
+      // and once more to leave the last one.
+      explorer.floor = depth;
+      let mut moved = 0.;
+      for _ in 0..=rng.random_range(0..children) {
+        let start = explorer.dlx().updates;
+        let ExploreNextChoiceResult::Continue = explorer.explore_next_choice() else {
+          dlx_unreachable!("Ran out of choices before trying all {children} of them");
+        };
+        moved = updates_since(&explorer, start);
+      }
+      updates += weight * (entered + (children + 1) as f64 * moved);
+      weight *= children as f64;
+    };
+    drop(explorer);
+    self.count_updates = count_updates;
+    probe
+  }
+
+  /// Estimates the size of the search tree and the number of solutions
+  /// without running the full search, using Knuth's random probes: each
+  /// probe follows a random path down the search tree, and assumes that
+  /// every node at each depth has as many children as the node it visited.
+  ///
+  /// Each probe gives an unbiased estimate, so the estimates get better with
+  /// more probes, but the variance can be very large for lopsided search
+  /// trees. Running time is estimated in updates (see
+  /// `SearchEstimate::updates`), the solver's main unit of work, but turning
+  /// that into seconds is up to the caller: time a search of a similar
+  /// problem with `SearchOptions::with_stats` to find how many updates the
+  /// machine does per second.
+  ///
+  /// The `Dlx` is left unmodified afterwards.
+  pub fn estimate_search<R: Rng + ?Sized>(&mut self, probes: usize, rng: &mut R) -> SearchEstimate {
+    let probes = (0..probes).map(|_| self.probe(rng)).collect::<Vec<_>>();
+    SearchEstimate::new(&probes)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use googletest::prelude::*;
+  use itertools::Itertools;
+  use rand::{rngs::StdRng, SeedableRng};
+
+  use crate::{Dlx, DlxIterator, HeaderType, SearchOptions};
+
+  #[gtest]
+  fn test_estimate_uniform_tree() {
+    // Every item can be covered by either of two subsets, so every probe sees
+    // the exact tree: 1 + 2 + 4 + 8 nodes, and 8 solutions.
+    let mut dlx = Dlx::new(
+      (0..3).map(|item| (item, HeaderType::Primary)),
+      (0..6).map(|name| (name, vec![name / 2])),
+    );
+    let mut rng = StdRng::seed_from_u64(1);
+
+    let estimate = dlx.estimate_search(10, &mut rng);
+    expect_eq!(estimate.probes(), 10);
+    expect_eq!(estimate.nodes(), 15.);
+    expect_eq!(estimate.nodes_variance(), 0.);
+    expect_eq!(estimate.solutions(), 8.);
+    expect_eq!(estimate.solutions_variance(), 0.);
+  }
+
+  #[gtest]
+  fn test_estimate_no_probes() {
+    let mut dlx = Dlx::new(vec![(0, HeaderType::Primary)], vec![(0, vec![0])]);
+    let estimate = dlx.estimate_search(0, &mut StdRng::seed_from_u64(1));
+    expect_eq!(estimate.nodes(), 0.);
+    expect_eq!(estimate.solutions(), 0.);
+  }
+
+  #[gtest]
+  fn test_estimate_close_to_actual() {
+    // Cover 8 items with singletons and pairs, and require 2 or 3 uses of the
+    // first item to exercise multiplicities.
+    let items = (0..8)
+      .map(|item| {
+        let header_type = if item == 0 {
+          HeaderType::BoundedPrimary { min: 2, max: 3 }
+        } else {
+          HeaderType::Primary
+        };
+        (item, header_type)
+      })
+      .collect_vec();
+    let subsets = (0..8)
+      .map(|item| vec![item])
+      .chain((0..8).tuple_combinations().map(|(a, b)| vec![a, b]))
+      .enumerate()
+      .collect_vec();
+    let mut dlx = Dlx::new(items, subsets);
+
+    let nodes = dlx.find_solutions_stepwise().count() as f64;
+    let solutions = dlx.count_solutions() as f64;
+    expect_that!(solutions, gt(0.));
+
+    let estimate = dlx.estimate_search(20_000, &mut StdRng::seed_from_u64(1));
+    let mut search = dlx.find_solutions_with(SearchOptions::new().with_stats());
+    search.by_ref().count();
+    let updates = search.stats().unwrap().updates() as f64;
+    drop(search);
+    expect_that!(estimate.nodes(), near(nodes, nodes * 0.1));
+    expect_that!(estimate.solutions(), near(solutions, solutions * 0.1));
+    expect_that!(estimate.nodes_variance(), gt(0.));
+    expect_that!(estimate.updates(), near(updates, updates * 0.1));
+
+    // The estimate must leave the problem unmodified.
+    expect_eq!(dlx.count_solutions() as f64, solutions);
+  }
+}
@@ -0,0 +1,126 @@
+use std::hash::Hash;
+
+use rand::{seq::SliceRandom, Rng};
+
+use super::{Dlx, DlxIteratorWithNames, SearchOptions};
+
+impl<I, N> Dlx<I, N>
+where
+  N: Clone + Eq + Hash,
+{
+  /// Returns true if exactly one solution includes all of `givens`.
+  fn givens_are_unique(&mut self, givens: &[N]) -> bool {
+    self
+      .with_givens(givens)
+      .is_ok_and(|mut with_givens| with_givens.count_solutions_up_to(2) == 1)
+  }
+
+  /// Generates a puzzle: a minimal set of givens (see `Dlx::with_givens`)
+  /// which only one solution includes, like the clues of a proper Sudoku.
+  ///
+  /// Starts from a solution found by a randomized search, and tries removing
+  /// its subsets one at a time in a random order, keeping each removal only
+  /// if the remaining givens still have a unique solution. Since removing
+  /// givens never removes solutions, no given can be left out of the result
+  /// without losing uniqueness, although smaller puzzles may exist.
+  ///
+  /// Subsets are named by `N`, so the names of the subsets must be unique.
+  /// Returns None if there are no solutions, or if the solution found isn't
+  /// unique even with all of its subsets given, which can only happen when
+  /// some items have multiplicities that allow more subsets to be added.
+  pub fn generate_puzzle<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Option<Vec<N>> {
+    let mut givens = self
+      .find_solutions_with(SearchOptions::new().with_seed(rng.random()))
+      .with_names()
+      .next()?;
+    if !self.givens_are_unique(&givens) {
+      return None;
+    }
+
+    givens.shuffle(rng);
+    let mut idx = 0;
+    while idx < givens.len() {
+      let removed = givens.remove(idx);
+      if !self.givens_are_unique(&givens) {
+        givens.insert(idx, removed);
+        idx += 1;
+      }
+    }
+    Some(givens)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::{fmt::Debug, hash::Hash};
+
+  use googletest::prelude::*;
+  use itertools::Itertools;
+  use rand::{rngs::StdRng, SeedableRng};
+
+  use crate::{dlx::test::random_problem, Dlx, HeaderType};
+
+  /// Checks that `givens` have a unique solution, and that leaving out any
+  /// one of them loses it.
+  fn expect_minimal_puzzle<I, N>(dlx: &mut Dlx<I, N>, givens: &[N])
+  where
+    I: Debug,
+    N: Clone + Debug + Eq + Hash,
+  {
+    expect_eq!(dlx.with_givens(givens).unwrap().count_solutions_up_to(2), 1);
+    for idx in 0..givens.len() {
+      let mut fewer = givens.to_vec();
+      fewer.remove(idx);
+      expect_eq!(dlx.with_givens(&fewer).unwrap().count_solutions_up_to(2), 2);
+    }
+  }
+
+  #[gtest]
+  fn test_generate_puzzle() {
+    // Latin squares of order 4, whose subsets place a symbol in a cell.
+    let subsets = (0..4)
+      .cartesian_product(0..4)
+      .cartesian_product(0..4)
+      .map(|((row, col), symbol)| {
+        (
+          (row, col, symbol),
+          vec![row * 4 + col, 16 + row * 4 + symbol, 32 + col * 4 + symbol],
+        )
+      })
+      .collect_vec();
+    let mut dlx = Dlx::new((0..48).map(|item| (item, HeaderType::Primary)), subsets);
+
+    let mut rng = StdRng::seed_from_u64(1);
+    let puzzles = (0..5)
+      .map(|_| dlx.generate_puzzle(&mut rng).unwrap())
+      .collect_vec();
+    for givens in &puzzles {
+      expect_minimal_puzzle(&mut dlx, givens);
+    }
+    expect_that!(puzzles.iter().all_equal(), eq(false));
+    expect_eq!(dlx.count_solutions(), 576);
+  }
+
+  #[gtest]
+  fn test_generate_puzzle_random_problems() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let mut generated = 0;
+    for seed in 0..200 {
+      let (items, subsets) = random_problem(seed);
+      let mut dlx = Dlx::new(items, subsets);
+      let num_solutions = dlx.count_solutions();
+      match dlx.generate_puzzle(&mut rng) {
+        Some(givens) => {
+          expect_minimal_puzzle(&mut dlx, &givens);
+          generated += 1;
+        }
+        None if num_solutions == 0 => {}
+        // Without a unique solution containing all of its subsets, some
+        // solution must be contained in another.
+        None => expect_that!(num_solutions, gt(1)),
+      }
+      expect_eq!(dlx.count_solutions(), num_solutions);
+    }
+    expect_that!(generated, gt(50));
+  }
+}
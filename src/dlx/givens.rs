@@ -0,0 +1,517 @@
+use std::{
+  collections::HashMap,
+  error::Error,
+  fmt::{self, Debug, Display, Formatter},
+  hash::Hash,
+  ops::Deref,
+};
+
+use super::{
+  CheckpointError, CheckpointValue, Dlx, DlxIterator, Node, NodeType, SearchCheckpoint,
+  SearchOptions, StepwiseDlxIterResult,
+};
+
+impl<I, N> Dlx<I, N> {
+  /// Returns true if the subset containing the node at `idx` can still be
+  /// chosen, i.e. none of its primary items have been used up, and it hasn't
+  /// been hidden by any other choice.
+  fn subset_available(&self, idx: usize) -> bool {
+    self.option_nodes(idx).all(|q| {
+      let Node::Normal {
+        node_type: NodeType::Body { top, purified, .. },
+        ..
+      } = self.body_node(q)
+      else {
+        dlx_unreachable!();
+      };
+      let top = *top as usize;
+
+      if self.header(top).is_primary() {
+        self.header(top).bound != 0 && self.in_item_list(q, top)
+      } else {
+        // Secondary nodes which have been purified to a matching color are
+        // left in place when their subset is hidden, but they never conflict.
+        // Covering an item used without a color leaves its list in place, but
+        // unlinks its header.
+        *purified || (self.item_linked(top) && self.in_item_list(q, top))
+      }
+    })
+  }
+
+  /// Iterates over the nodes of the subset containing the node at `idx`,
+  /// starting from `idx`.
+  fn option_nodes(&self, idx: usize) -> impl Iterator<Item = usize> + '_ {
+    std::iter::once(idx).chain(self.iterate_items(idx))
+  }
+
+  /// Returns true if the header `top` is still in the items list, i.e. it
+  /// hasn't been covered.
+  fn item_linked(&self, top: usize) -> bool {
+    let prev = self.header(top).node.prev as usize;
+    self.header(prev).node.next as usize == top
+  }
+
+  fn in_item_list(&self, idx: usize, top: usize) -> bool {
+    let mut p = self.body_header(top).next();
+    while p != top {
+      if p == idx {
+        return true;
+      }
+      p = self.body_node(p).next();
+    }
+    false
+  }
+
+  /// Chooses the subset containing the node at `idx` outside of any search,
+  /// removing it from every item list and committing all of its items.
+  fn force_subset(&mut self, idx: usize) {
+    let Node::Normal {
+      item_node,
+      node_type: NodeType::Body { top, purified, .. },
+    } = self.body_node(idx)
+    else {
+      dlx_unreachable!();
+    };
+    let top = *top as usize;
+
+    // `hide` leaves the node it is given in place, so remove that one here.
+    if !purified {
+      let (prev_idx, next_idx) = (item_node.prev, item_node.next);
+      self.node_mut(prev_idx).set_next(next_idx);
+      self.node_mut(next_idx).set_prev(prev_idx);
+    }
+    let len_mut = self.body_header_mut(top).len_mut();
+    *len_mut = len_mut.wrapping_sub(1);
+    self.hide(idx);
+
+    self.commit(idx, top);
+    self.cover_remaining_choices(idx);
+    self.forced.push(idx);
+  }
+
+  /// Reverts the last `force_subset`.
+  fn unforce_last_subset(&mut self) {
+    let Some(idx) = self.forced.pop() else {
+      dlx_unreachable!("No forced subset to revert");
+    };
+    let top = match self.body_node(idx) {
+      Node::Normal {
+        node_type: NodeType::Body { top, .. },
+        ..
+      } => *top as usize,
+      _ => dlx_unreachable!(),
+    };
+    self.uncover_remaining_choices(idx);
+    self.uncommit(idx, top);
+
+    self.unhide(idx);
+    let len_mut = self.body_header_mut(top).len_mut();
+    *len_mut = len_mut.wrapping_add(1);
+    let Node::Normal {
+      item_node,
+      node_type: NodeType::Body { purified, .. },
+    } = self.body_node(idx)
+    else {
+      dlx_unreachable!();
+    };
+    if !purified {
+      let (prev_idx, next_idx) = (item_node.prev, item_node.next);
+      self.node_mut(prev_idx).set_next(idx);
+      self.node_mut(next_idx).set_prev(idx);
+    }
+  }
+}
+
+impl<I, N> Dlx<I, N>
+where
+  N: Clone + Eq + Hash,
+{
+  /// Returns the first node of each subset by name, or None for subsets
+  /// without any items. The index is built once and kept, since the nodes of
+  /// a subset never move.
+  fn subset_index(&self) -> &HashMap<N, Option<usize>> {
+    self.subset_index.get_or_init(|| {
+      let mut index = HashMap::new();
+      let mut start = self.headers.len();
+      for idx in self.headers.len()..self.body.len() {
+        if let Node::Boundary { name, .. } = self.body_node(idx) {
+          index.insert(name.clone().unwrap(), (start != idx).then_some(start));
+          start = idx + 1;
+        }
+      }
+      index
+    })
+  }
+
+  /// Forces the subsets named in `givens` to be part of every solution, like
+  /// the clues of a Sudoku puzzle. The returned guard searches for the ways
+  /// to complete the givens, and restores this `Dlx` when it is dropped.
+  ///
+  /// Returns `GivensError::UnknownSubset` if a given doesn't name a subset,
+  /// and `GivensError::ConflictingGiven` if a given can't be chosen alongside
+  /// the givens before it.
+  pub fn with_givens(&mut self, givens: &[N]) -> Result<DlxWithGivens<'_, I, N>, GivensError<N>> {
+    let index = self.subset_index();
+    let mut given_nodes = Vec::with_capacity(givens.len());
+    for name in givens {
+      match index.get(name) {
+        Some(&Some(idx)) => given_nodes.push((name, idx)),
+        // Subsets with no items can always be chosen, so don't need forcing.
+        Some(None) => {}
+        None => return Err(GivensError::UnknownSubset(name.clone())),
+      }
+    }
+
+    let with_givens = DlxWithGivens { dlx: self };
+    for (name, idx) in given_nodes {
+      if !with_givens.dlx.subset_available(idx) {
+        // Dropping `with_givens` undoes the givens forced so far.
+        return Err(GivensError::ConflictingGiven(name.clone()));
+      }
+      with_givens.dlx.force_subset(idx);
+    }
+    Ok(with_givens)
+  }
+}
+
+/// Reasons the givens passed to `Dlx::with_givens` can't be forced.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GivensError<N> {
+  /// A given doesn't name a subset.
+  UnknownSubset(N),
+  /// A given can't be chosen together with the givens before it.
+  ConflictingGiven(N),
+}
+
+impl<N> Display for GivensError<N>
+where
+  N: Debug,
+{
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      GivensError::UnknownSubset(name) => write!(f, "Unknown subset {name:?}"),
+      GivensError::ConflictingGiven(name) => {
+        write!(f, "Given subset {name:?} conflicts with an earlier given")
+      }
+    }
+  }
+}
+
+impl<N> Error for GivensError<N> where N: Debug {}
+
+/// A `Dlx` with some subsets forced to be chosen, created by
+/// `Dlx::with_givens`. Solutions found through this only list the subsets
+/// chosen in addition to the givens.
+pub struct DlxWithGivens<'a, I, N> {
+  dlx: &'a mut Dlx<I, N>,
+}
+
+impl<I, N> DlxWithGivens<'_, I, N> {
+  pub fn find_solutions(&mut self) -> impl DlxIterator<I, N> + '_ {
+    self.dlx.find_solutions()
+  }
+
+  pub fn find_solutions_with(&mut self, options: SearchOptions<I>) -> impl DlxIterator<I, N> + '_ {
+    self.dlx.find_solutions_with(options)
+  }
+
+  pub fn find_solutions_stepwise(
+    &mut self,
+  ) -> impl DlxIterator<I, N, StepwiseDlxIterResult<Vec<usize>>> + '_ {
+    self.dlx.find_solutions_stepwise()
+  }
+
+  pub fn find_solutions_stepwise_with(
+    &mut self,
+    options: SearchOptions<I>,
+  ) -> impl DlxIterator<I, N, StepwiseDlxIterResult<Vec<usize>>> + '_ {
+    self.dlx.find_solutions_stepwise_with(options)
+  }
+
+  pub fn count_solutions(&mut self) -> u64 {
+    self.dlx.count_solutions()
+  }
+
+  pub fn count_solutions_up_to(&mut self, limit: u64) -> u64 {
+    self.dlx.count_solutions_up_to(limit)
+  }
+}
+
+impl<I, N> DlxWithGivens<'_, I, N>
+where
+  I: CheckpointValue,
+  N: CheckpointValue,
+{
+  /// Picks up a search from `checkpoint`, which must have been taken from a
+  /// search of the same problem with the same givens (see
+  /// `Dlx::resume_solutions`).
+  pub fn resume_solutions(
+    &mut self,
+    checkpoint: &SearchCheckpoint,
+  ) -> Result<impl DlxIterator<I, N> + '_, CheckpointError> {
+    self.dlx.resume_solutions(checkpoint)
+  }
+
+  pub fn resume_solutions_with(
+    &mut self,
+    checkpoint: &SearchCheckpoint,
+    options: SearchOptions<I>,
+  ) -> Result<impl DlxIterator<I, N> + '_, CheckpointError> {
+    self.dlx.resume_solutions_with(checkpoint, options)
+  }
+}
+
+impl<I, N> DlxWithGivens<'_, I, N>
+where
+  N: Clone,
+{
+  /// Returns the names of the given subsets, in the order they were given.
+  pub fn given_names(&self) -> Vec<N> {
+    self.dlx.solution_names(&self.dlx.forced)
+  }
+}
+
+impl<I, N> Deref for DlxWithGivens<'_, I, N> {
+  type Target = Dlx<I, N>;
+
+  fn deref(&self) -> &Dlx<I, N> {
+    self.dlx
+  }
+}
+
+impl<I, N> Debug for DlxWithGivens<'_, I, N>
+where
+  I: Debug,
+  N: Debug,
+{
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.debug_struct("DlxWithGivens")
+      .field("dlx", &self.dlx)
+      .field("givens", &self.dlx.forced)
+      .finish()
+  }
+}
+
+impl<I, N> Drop for DlxWithGivens<'_, I, N> {
+  fn drop(&mut self) {
+    while !self.dlx.forced.is_empty() {
+      self.dlx.unforce_last_subset();
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use googletest::prelude::*;
+  use itertools::Itertools;
+
+  use crate::{
+    dlx::test::random_problem, ColorItem, Constraint, Dlx, DlxIteratorWithNames, GivensError,
+    HeaderType,
+  };
+
+  fn sample() -> Dlx<char, u32> {
+    Dlx::new(
+      vec![
+        ('p', HeaderType::Primary),
+        ('q', HeaderType::Primary),
+        ('r', HeaderType::Primary),
+      ],
+      vec![
+        (0, vec!['p', 'q']),
+        (1, vec!['p']),
+        (2, vec!['q']),
+        (3, vec!['r']),
+        (4, vec!['q', 'r']),
+      ],
+    )
+  }
+
+  #[gtest]
+  fn test_givens() {
+    let mut dlx = sample();
+
+    let mut with_givens = dlx.with_givens(&[1]).unwrap();
+    expect_that!(with_givens.given_names(), elements_are![&1]);
+    expect_that!(
+      with_givens
+        .find_solutions()
+        .with_names()
+        .map(|solution| solution.into_iter().sorted().collect_vec())
+        .collect_vec(),
+      unordered_elements_are![elements_are![&2, &3], elements_are![&4]]
+    );
+    drop(with_givens);
+
+    let mut with_givens = dlx.with_givens(&[3, 1]).unwrap();
+    expect_that!(
+      with_givens.find_solutions().with_names().collect_vec(),
+      elements_are![elements_are![&2]]
+    );
+    expect_eq!(with_givens.count_solutions_up_to(2), 1);
+    drop(with_givens);
+
+    // Dropping the givens restores the original problem.
+    expect_eq!(dlx.count_solutions(), 3);
+  }
+
+  #[gtest]
+  fn test_givens_errors() {
+    let mut dlx = sample();
+
+    expect_that!(
+      dlx.with_givens(&[1, 7]).unwrap_err(),
+      eq(&GivensError::UnknownSubset(7))
+    );
+    expect_that!(
+      dlx.with_givens(&[3, 0, 4]).unwrap_err(),
+      eq(&GivensError::ConflictingGiven(4))
+    );
+    expect_that!(
+      dlx.with_givens(&[1, 1]).unwrap_err(),
+      eq(&GivensError::ConflictingGiven(1))
+    );
+    expect_eq!(dlx.count_solutions(), 3);
+  }
+
+  #[gtest]
+  fn test_givens_colors() {
+    let mut dlx = Dlx::new(
+      vec![
+        ('p', HeaderType::Primary),
+        ('q', HeaderType::Primary),
+        ('r', HeaderType::Primary),
+        ('x', HeaderType::Secondary),
+      ],
+      vec![
+        (0, vec![Constraint::Primary('p')]),
+        (
+          1,
+          vec![Constraint::Primary('q'), ColorItem::new('x', 1).into()],
+        ),
+        (
+          2,
+          vec![Constraint::Primary('r'), ColorItem::new('x', 1).into()],
+        ),
+        (
+          3,
+          vec![Constraint::Primary('r'), ColorItem::new('x', 2).into()],
+        ),
+      ],
+    );
+
+    let mut with_givens = dlx.with_givens(&[1]).unwrap();
+    expect_that!(
+      with_givens.find_solutions().with_names().collect_vec(),
+      elements_are![unordered_elements_are![&0, &2]]
+    );
+    drop(with_givens);
+
+    expect_that!(
+      dlx.with_givens(&[3, 1]).unwrap_err(),
+      eq(&GivensError::ConflictingGiven(1))
+    );
+    expect_that!(dlx.with_givens(&[2, 1]), ok(anything()));
+  }
+
+  #[gtest]
+  fn test_givens_uncolored_secondary() {
+    let mut dlx = Dlx::new(
+      vec![
+        ('p', HeaderType::Primary),
+        ('q', HeaderType::Primary),
+        ('x', HeaderType::Secondary),
+      ],
+      vec![
+        (0, vec!['p'.into(), Constraint::SecondaryUncolored('x')]),
+        (1, vec!['q'.into(), ColorItem::new('x', 1).into()]),
+        (2, vec![Constraint::Primary('q')]),
+      ],
+    );
+
+    let mut with_givens = dlx.with_givens(&[0]).unwrap();
+    expect_that!(
+      with_givens.find_solutions().with_names().collect_vec(),
+      elements_are![elements_are![&2]]
+    );
+    drop(with_givens);
+
+    expect_that!(
+      dlx.with_givens(&[0, 1]).unwrap_err(),
+      eq(&GivensError::ConflictingGiven(1))
+    );
+    expect_that!(
+      dlx.with_givens(&[1, 0]).unwrap_err(),
+      eq(&GivensError::ConflictingGiven(0))
+    );
+    expect_eq!(dlx.count_solutions(), 1);
+  }
+
+  #[gtest]
+  fn test_givens_bounded_primary() {
+    let mut dlx = Dlx::new(
+      vec![('a', HeaderType::BoundedPrimary { min: 2, max: 3 })],
+      vec![
+        (0, vec!['a']),
+        (1, vec!['a']),
+        (2, vec!['a']),
+        (3, vec!['a']),
+      ],
+    );
+
+    let mut with_givens = dlx.with_givens(&[2]).unwrap();
+    // One or two more of the other three subsets.
+    expect_eq!(with_givens.count_solutions(), 6);
+    drop(with_givens);
+
+    expect_that!(
+      dlx.with_givens(&[0, 1, 2, 3]).unwrap_err(),
+      eq(&GivensError::ConflictingGiven(3))
+    );
+    expect_eq!(dlx.count_solutions(), 10);
+  }
+
+  #[gtest]
+  fn test_givens_match_filtered_solutions() {
+    for seed in 0..100 {
+      let (items, subsets) = random_problem(seed);
+      let mut dlx = Dlx::new(items, subsets);
+      let solutions = dlx
+        .find_solutions()
+        .with_names()
+        .map(|solution| solution.into_iter().sorted().collect_vec())
+        .sorted()
+        .collect_vec();
+
+      // Take the givens from the first solution, if there is one.
+      let Some(givens) = solutions
+        .first()
+        .map(|solution| solution[..solution.len() / 2].to_vec())
+      else {
+        continue;
+      };
+      let mut with_givens = dlx.with_givens(&givens).unwrap();
+      let completions = with_givens
+        .find_solutions()
+        .with_names()
+        .map(|completion| {
+          completion
+            .into_iter()
+            .chain(givens.iter().copied())
+            .sorted()
+            .collect_vec()
+        })
+        .sorted()
+        .collect_vec();
+      drop(with_givens);
+
+      let expected = solutions
+        .iter()
+        .filter(|solution| givens.iter().all(|given| solution.contains(given)))
+        .cloned()
+        .collect_vec();
+      expect_eq!(completions, expected, "seed {seed}");
+      expect_eq!(dlx.count_solutions(), solutions.len() as u64, "seed {seed}");
+    }
+  }
+}
@@ -0,0 +1,252 @@
+use std::{borrow::BorrowMut, collections::BinaryHeap};
+
+use super::{ChooseNextItemResult, Dlx, DlxExplorer, ExploreNextChoiceResult, Node, NodeType};
+
+impl<I, N> Dlx<I, N> {
+  /// Returns a lower bound on the total cost of the subsets still needed to
+  /// cover every remaining primary item, or None if some item can no longer
+  /// be covered enough times.
+  ///
+  /// Every remaining item which must still be covered needs at least one more
+  /// subset, and since item lists are sorted by cost, the cheapest such
+  /// subset is the first in the item's list.
+  fn remaining_cost_lower_bound(&self) -> Option<u64> {
+    let mut lower_bound = 0;
+    let mut item = self.header(0).node.next as usize;
+    while item != 0 {
+      let header = self.header(item);
+      if header.bound > header.slack {
+        let first = self.body_header(item).next();
+        if first == item {
+          return None;
+        }
+        lower_bound = lower_bound.max(self.subset_cost(first));
+      }
+      item = header.node.next as usize;
+    }
+    Some(lower_bound)
+  }
+}
+
+impl<D, I, N> DlxExplorer<D, I, N>
+where
+  D: BorrowMut<Dlx<I, N>>,
+{
+  /// Returns the cost of the choice made at the deepest level of the search,
+  /// which is 0 if no subset was chosen for its item.
+  fn last_choice_cost(&self) -> u64 {
+    let dlx = self.dlx();
+    match self.partial_solution.last() {
+      Some(&p)
+        if matches!(
+          dlx.node(p),
+          Node::Normal {
+            node_type: NodeType::Body { .. },
+            ..
+          }
+        ) =>
+      {
+        dlx.subset_cost(p)
+      }
+      _ => 0,
+    }
+  }
+}
+
+/// The total cost of the subsets chosen at each level of a search, kept in
+/// step with the explorer's partial solution so the cost so far doesn't have
+/// to be summed at every node.
+#[derive(Default)]
+struct RunningCosts(Vec<u64>);
+
+impl RunningCosts {
+  /// Updates the totals after the explorer has replaced the choice at its
+  /// deepest level, by `explore_next_choice` or `choose_next_item`. Only the
+  /// deepest level changes in either case.
+  fn sync<D, I, N>(&mut self, explorer: &DlxExplorer<D, I, N>)
+  where
+    D: BorrowMut<Dlx<I, N>>,
+  {
+    let depth = explorer.partial_solution.len();
+    self.0.truncate(depth.saturating_sub(1));
+    if depth != 0 {
+      self.0.push(self.total() + explorer.last_choice_cost());
+    }
+  }
+
+  /// The total cost of the subsets chosen so far.
+  fn total(&self) -> u64 {
+    self.0.last().copied().unwrap_or(0)
+  }
+}
+
+impl<I, N> Dlx<I, N>
+where
+  N: Clone,
+{
+  /// Finds the `k` solutions with the lowest total cost, where the cost of a
+  /// solution is the sum of the costs of its subsets (see
+  /// `Dlx::new_with_costs`). Returns `(cost, subset names)` pairs in order of
+  /// increasing cost, with fewer than `k` entries if there aren't `k`
+  /// solutions.
+  ///
+  /// This is a branch-and-bound search: once `k` solutions have been found,
+  /// any branch whose cost so far plus a lower bound on the cost of covering
+  /// the remaining items can't beat the `k`-th best is skipped.
+  pub fn find_min_cost_solutions(&mut self, k: usize) -> Vec<(u64, Vec<N>)> {
+    if k == 0 {
+      return Vec::new();
+    }
+
+    // Max-heap of the best solutions found so far, so the k-th best is at the
+    // top.
+    let mut best = BinaryHeap::new();
+    let mut explorer = DlxExplorer::new(&mut *self);
+    let mut costs = RunningCosts::default();
+    let mut started = false;
+    loop {
+      if started {
+        if let ExploreNextChoiceResult::Done = explorer.explore_next_choice() {
+          break;
+        }
+        costs.sync(&explorer);
+
+        let Some(lower_bound) = explorer.dlx().remaining_cost_lower_bound() else {
+          continue;
+        };
+        if best.len() == k
+          && best
+            .peek()
+            .is_some_and(|(kth_cost, _)| costs.total() + lower_bound >= *kth_cost)
+        {
+          continue;
+        }
+      }
+      started = true;
+
+      match explorer.choose_next_item() {
+        ChooseNextItemResult::Continue => costs.sync(&explorer),
+        ChooseNextItemResult::FoundSolution => {
+          best.push((costs.total(), explorer.partial_solution().clone()));
+          if best.len() > k {
+            best.pop();
+          }
+        }
+      }
+    }
+    drop(explorer);
+
+    best
+      .into_sorted_vec()
+      .into_iter()
+      .map(|(cost, solution)| (cost, self.solution_names(&solution)))
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::collections::HashMap;
+
+  use googletest::prelude::*;
+  use itertools::Itertools;
+
+  use crate::{Dlx, DlxIteratorWithNames, HeaderType};
+
+  #[gtest]
+  fn test_min_cost_simple() {
+    let mut dlx = Dlx::new_with_costs(
+      vec![('p', HeaderType::Primary), ('q', HeaderType::Primary)],
+      vec![
+        (0, 5, vec!['p', 'q']),
+        (1, 1, vec!['p']),
+        (2, 2, vec!['q']),
+        (3, 3, vec!['q']),
+      ],
+    );
+
+    expect_that!(
+      dlx.find_min_cost_solutions(1),
+      elements_are![(eq(&3), elements_are![&1, &2])]
+    );
+    expect_that!(
+      dlx.find_min_cost_solutions(10),
+      elements_are![
+        (eq(&3), elements_are![&1, &2]),
+        (eq(&4), elements_are![&1, &3]),
+        (eq(&5), elements_are![&0]),
+      ]
+    );
+    expect_that!(dlx.find_min_cost_solutions(0), empty());
+  }
+
+  #[gtest]
+  fn test_min_cost_no_solutions() {
+    let mut dlx = Dlx::new_with_costs(
+      vec![('p', HeaderType::Primary), ('q', HeaderType::Primary)],
+      vec![(0, 1, vec!['p']), (1, 1, vec!['p'])],
+    );
+    expect_that!(dlx.find_min_cost_solutions(3), empty());
+  }
+
+  #[gtest]
+  fn test_min_cost_matches_exhaustive_search() {
+    // Cover 6 items with singletons and pairs, with costs that favor neither.
+    let items = (0..6).map(|item| (item, HeaderType::Primary)).collect_vec();
+    let subsets = (0..6)
+      .map(|item| vec![item])
+      .chain((0..6).tuple_combinations().map(|(a, b)| vec![a, b]))
+      .enumerate()
+      .map(|(name, subset)| (name, (name as u64 * 7) % 11, subset))
+      .collect_vec();
+    let costs: HashMap<_, _> = subsets
+      .iter()
+      .map(|(name, cost, _)| (*name, *cost))
+      .collect();
+
+    let mut dlx = Dlx::new_with_costs(items, subsets);
+    let expected_costs = dlx
+      .find_solutions()
+      .with_names()
+      .map(|solution| solution.iter().map(|name| costs[name]).sum::<u64>())
+      .sorted()
+      .collect_vec();
+    expect_eq!(expected_costs.len(), 76);
+
+    for k in [1, 5, 20, 100] {
+      let solutions = dlx.find_min_cost_solutions(k);
+      expect_eq!(
+        solutions.iter().map(|(cost, _)| *cost).collect_vec(),
+        expected_costs.iter().copied().take(k).collect_vec()
+      );
+      for (cost, solution) in solutions {
+        expect_eq!(cost, solution.iter().map(|name| costs[name]).sum::<u64>());
+      }
+    }
+  }
+
+  #[gtest]
+  fn test_min_cost_bounded_primary() {
+    let mut dlx = Dlx::new_with_costs(
+      vec![
+        ('a', HeaderType::BoundedPrimary { min: 2, max: 3 }),
+        ('b', HeaderType::Primary),
+      ],
+      vec![
+        (0, 4, vec!['a']),
+        (1, 1, vec!['a']),
+        (2, 2, vec!['a']),
+        (3, 3, vec!['a', 'b']),
+        (4, 0, vec!['b']),
+      ],
+    );
+
+    expect_that!(
+      dlx.find_min_cost_solutions(2),
+      elements_are![
+        (eq(&3), unordered_elements_are![&1, &2, &4]),
+        (eq(&4), unordered_elements_are![&1, &3]),
+      ]
+    );
+  }
+}
@@ -0,0 +1,250 @@
+use rayon::prelude::*;
+
+use std::borrow::BorrowMut;
+
+use super::{
+  ChooseNextItemResult, Dlx, DlxExplorer, DlxIteratorImpl, DlxStepResult, ExploreNextChoiceResult,
+};
+
+/// The number of jobs to aim for per thread when splitting up the search
+/// tree, so that threads which finish early have work to steal.
+const JOBS_PER_THREAD: usize = 16;
+
+/// Splitting never goes deeper than this, even if the top of the search tree
+/// is too narrow to produce enough jobs.
+const MAX_SPLIT_DEPTH: usize = 32;
+
+impl<D, I, N> DlxExplorer<D, I, N>
+where
+  D: BorrowMut<Dlx<I, N>>,
+{
+  /// Replays the choices in `path`, which must be a `partial_solution`
+  /// recorded from an explorer over an identical `Dlx` just after it chose a
+  /// subset (or found a solution), and restricts the rest of the search to
+  /// the subtree below it.
+  fn descend(&mut self, path: &[usize]) {
+    debug_assert!(self.partial_solution.is_empty());
+    for (depth, &target) in path.iter().enumerate() {
+      let ChooseNextItemResult::Continue = self.choose_next_item() else {
+        dlx_unreachable!("Found a solution before the end of path {path:?}");
+      };
+      self.floor = depth;
+      loop {
+        let ExploreNextChoiceResult::Continue = self.explore_next_choice() else {
+          dlx_unreachable!("Choice {target} not found at depth {depth}");
+        };
+        if self.partial_solution[depth] == target {
+          break;
+        }
+      }
+    }
+    self.floor = path.len();
+  }
+}
+
+impl<I, N> Dlx<I, N>
+where
+  I: Clone,
+  N: Clone,
+{
+  /// Lists the paths to the children of the node of the search tree at the
+  /// end of `path`, or to the solution there if it is one. Each path comes
+  /// with whether it was cut off before reaching a solution or dead end.
+  fn search_children(&mut self, path: &[usize]) -> Vec<(Vec<usize>, bool)> {
+    let mut explorer = DlxExplorer::new(&mut *self);
+    explorer.descend(path);
+    let mut children = Vec::new();
+
+    let mut started = false;
+    loop {
+      if started {
+        if let ExploreNextChoiceResult::Done = explorer.explore_next_choice() {
+          break;
+        }
+        if explorer.partial_solution().len() > path.len() {
+          children.push((explorer.partial_solution().clone(), true));
+          continue;
+        }
+      }
+      started = true;
+
+      if let ChooseNextItemResult::FoundSolution = explorer.choose_next_item() {
+        children.push((explorer.partial_solution().clone(), false));
+      }
+    }
+
+    children
+  }
+
+  /// Partitions the search tree into independent jobs, each given by the
+  /// path to the root of its subtree. The subtrees are disjoint and together
+  /// cover the whole search tree.
+  ///
+  /// The tree is split one level at a time, expanding the paths which were
+  /// cut off at the previous level, until there are enough jobs.
+  fn split_search(&self) -> Vec<Vec<usize>> {
+    let target_jobs = rayon::current_num_threads() * JOBS_PER_THREAD;
+    let mut dlx = self.clone();
+    let mut frontier = vec![(Vec::new(), true)];
+    for _ in 0..MAX_SPLIT_DEPTH {
+      if frontier.len() >= target_jobs || !frontier.iter().any(|&(_, truncated)| truncated) {
+        break;
+      }
+      let mut next = Vec::with_capacity(frontier.len());
+      for (path, truncated) in frontier {
+        if truncated {
+          next.extend(dlx.search_children(&path));
+        } else {
+          next.push((path, false));
+        }
+      }
+      frontier = next;
+    }
+    frontier.into_iter().map(|(path, _)| path).collect()
+  }
+}
+
+impl<I, N> Dlx<I, N>
+where
+  I: Clone + Send + Sync,
+  N: Clone + Send + Sync,
+{
+  /// Finds all solutions in parallel, yielding the same solutions as
+  /// `find_solutions` (in no particular order). The top levels of the search
+  /// tree are split into jobs which are distributed among rayon's thread
+  /// pool, each job searching its subtree on its own clone of this `Dlx`.
+  /// Solutions are yielded as each job finds them, so no job holds on to the
+  /// solutions in its subtree.
+  ///
+  /// Solutions are lists of node indices, which can be mapped to subset names
+  /// with `Dlx::solution_names`.
+  pub fn par_solutions(&self) -> impl ParallelIterator<Item = Vec<usize>> + '_ {
+    self.split_search().into_par_iter().flat_map_iter(|path| {
+      let mut iter = DlxIteratorImpl::new(self.clone());
+      iter.explorer.descend(&path);
+      iter
+    })
+  }
+
+  /// Counts the solutions in parallel, like `par_solutions`, but without
+  /// materializing any of them.
+  pub fn par_count_solutions(&self) -> u64 {
+    self
+      .split_search()
+      .into_par_iter()
+      .map_init(
+        || self.clone(),
+        |dlx, path| {
+          let mut explorer = DlxExplorer::new(&mut *dlx);
+          explorer.descend(&path);
+          let mut count = 0;
+          loop {
+            match explorer.step() {
+              DlxStepResult::Continue => {}
+              DlxStepResult::FoundSolution(_) => count += 1,
+              DlxStepResult::Done => return count,
+            }
+          }
+        },
+      )
+      .sum()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use googletest::prelude::*;
+  use itertools::Itertools;
+  use rayon::prelude::*;
+
+  use crate::{dlx::test::random_problem, nqueens, Dlx, HeaderType};
+
+  #[gtest]
+  fn test_par_solutions_matches_sequential() {
+    let mut dlx = nqueens(6);
+    let expected = dlx.find_solutions().sorted().collect_vec();
+    expect_eq!(expected.len(), 4);
+    expect_eq!(
+      dlx
+        .par_solutions()
+        .collect::<Vec<_>>()
+        .into_iter()
+        .sorted()
+        .collect_vec(),
+      expected
+    );
+  }
+
+  #[gtest]
+  fn test_par_solutions_matches_sequential_random() {
+    for seed in 0..100 {
+      let (items, subsets) = random_problem(seed);
+      let mut dlx = Dlx::new(items, subsets);
+      let expected = dlx.find_solutions().sorted().collect_vec();
+      expect_eq!(
+        dlx
+          .par_solutions()
+          .collect::<Vec<_>>()
+          .into_iter()
+          .sorted()
+          .collect_vec(),
+        expected,
+        "seed {seed}"
+      );
+      expect_eq!(
+        dlx.par_count_solutions(),
+        expected.len() as u64,
+        "seed {seed}"
+      );
+    }
+  }
+
+  #[gtest]
+  fn test_par_count_solutions() {
+    let mut dlx = nqueens(8);
+    expect_eq!(dlx.par_count_solutions(), 92);
+    expect_eq!(dlx.count_solutions(), 92);
+  }
+
+  #[gtest]
+  fn test_par_solutions_with_names() {
+    let dlx = Dlx::new(
+      vec![
+        ('p', HeaderType::Primary),
+        ('q', HeaderType::Primary),
+        ('r', HeaderType::Primary),
+      ],
+      vec![
+        (0, vec!['p', 'q']),
+        (1, vec!['p']),
+        (2, vec!['p', 'q']),
+        (3, vec!['r']),
+      ],
+    );
+
+    let solutions = dlx
+      .par_solutions()
+      .map(|solution| {
+        dlx
+          .solution_names(&solution)
+          .into_iter()
+          .sorted()
+          .collect_vec()
+      })
+      .collect::<Vec<_>>();
+    expect_that!(
+      solutions,
+      unordered_elements_are![elements_are![&0, &3], elements_are![&2, &3]]
+    );
+  }
+
+  #[gtest]
+  fn test_par_empty_problem() {
+    let dlx: Dlx<u32, u32> = Dlx::new::<_, _, Vec<_>, u32>(vec![], vec![]);
+    expect_eq!(dlx.par_count_solutions(), 1);
+    expect_that!(
+      dlx.par_solutions().collect::<Vec<_>>(),
+      elements_are![empty()]
+    );
+  }
+}
@@ -0,0 +1,529 @@
+use std::{fmt::Debug, hash::Hash};
+
+use super::{ColorItem, Constraint, Dlx, DlxIteratorWithNames, HeaderType, Node, NodeType};
+
+/// An item of the problem being preprocessed.
+#[derive(Clone, Debug)]
+struct ItemState {
+  header_type: HeaderType,
+  /// The number of times a primary item still has to be covered, and may
+  /// still be covered, after the forced options.
+  min: u32,
+  max: u32,
+  /// False once the forced options leave no room for any other option to
+  /// use this item, in which case it is left out of the reduced problem.
+  active: bool,
+}
+
+/// An option of the problem being preprocessed, as `(item, color)` pairs.
+#[derive(Clone, Debug)]
+struct OptionState {
+  cost: u64,
+  cells: Vec<(usize, Option<u32>)>,
+  alive: bool,
+}
+
+/// The bookkeeping of `Dlx::preprocess`, with items and options indexed in
+/// the order they appear in the `Dlx`.
+struct Preprocessor {
+  items: Vec<ItemState>,
+  options: Vec<OptionState>,
+  /// The options of each item, including dead ones.
+  item_options: Vec<Vec<usize>>,
+  forced: Vec<usize>,
+  removed: Vec<usize>,
+}
+
+impl Preprocessor {
+  fn new<I, N>(dlx: &Dlx<I, N>) -> Self {
+    let num_items = dlx.headers.len() - 2;
+    let items = dlx.headers[1..=num_items]
+      .iter()
+      .map(|header| {
+        let (min, max) = match header.header_type {
+          HeaderType::Primary => (1, 1),
+          HeaderType::BoundedPrimary { min, max } => (min, max),
+          HeaderType::Secondary => (0, 0),
+        };
+        ItemState {
+          header_type: header.header_type,
+          min,
+          max,
+          active: true,
+        }
+      })
+      .collect::<Vec<_>>();
+
+    let mut options = Vec::new();
+    let mut item_options = vec![Vec::new(); num_items];
+    let mut cells: Vec<(usize, Option<u32>)> = Vec::new();
+    for idx in dlx.headers.len()..dlx.body.len() {
+      match dlx.body_node(idx) {
+        Node::Boundary { cost, .. } => {
+          for &(item, _) in &cells {
+            item_options[item].push(options.len());
+          }
+          options.push(OptionState {
+            cost: *cost,
+            cells: std::mem::take(&mut cells),
+            alive: true,
+          });
+        }
+        Node::Normal {
+          node_type: NodeType::Body { top, color, .. },
+          ..
+        } => cells.push((*top as usize - 1, *color)),
+        Node::Normal {
+          node_type: NodeType::Header { .. },
+          ..
+        } => dlx_unreachable!("Unexpected header in body"),
+      }
+    }
+
+    Self {
+      items,
+      options,
+      item_options,
+      forced: Vec::new(),
+      removed: Vec::new(),
+    }
+  }
+
+  fn is_primary(&self, item: usize) -> bool {
+    self.items[item].header_type.is_primary()
+  }
+
+  fn alive_options(&self, item: usize) -> impl Iterator<Item = usize> + '_ {
+    self.item_options[item]
+      .iter()
+      .copied()
+      .filter(|&option| self.options[option].alive)
+  }
+
+  /// Whether two options which both use `item`, with the given colors, can't
+  /// be chosen together.
+  fn conflicts_on(&self, item: usize, color: Option<u32>, other_color: Option<u32>) -> bool {
+    if self.is_primary(item) {
+      self.items[item].max <= 1
+    } else {
+      color.is_none() || color != other_color
+    }
+  }
+
+  fn remove(&mut self, option: usize) {
+    self.options[option].alive = false;
+    self.removed.push(option);
+  }
+
+  /// Commits to `option`, which is the only way left to cover one of its
+  /// items, removing every option it conflicts with.
+  fn force(&mut self, option: usize) {
+    self.options[option].alive = false;
+    self.forced.push(option);
+    for (item, color) in self.options[option].cells.clone() {
+      let state = &mut self.items[item];
+      if state.header_type.is_primary() {
+        state.min = state.min.saturating_sub(1);
+        state.max -= 1;
+        state.active = state.max > 0;
+      } else {
+        state.active = color.is_some();
+      }
+      // The forced option has already used up its share of a primary item,
+      // so only options which would exceed what is left conflict with it.
+      let conflicting = self
+        .alive_options(item)
+        .filter(|&other| {
+          let state = &self.items[item];
+          !state.active
+            || (!state.header_type.is_primary()
+              && self.conflicts_on(item, color, self.option_color(other, item)))
+        })
+        .collect::<Vec<_>>();
+      for other in conflicting {
+        self.remove(other);
+      }
+    }
+  }
+
+  /// The color `option` gives `item`, which it must contain.
+  fn option_color(&self, option: usize, item: usize) -> Option<u32> {
+    self.options[option]
+      .cells
+      .iter()
+      .find_map(|&(i, color)| (i == item).then_some(color))
+      .flatten()
+  }
+
+  /// Forces the options of every primary item which has exactly one option
+  /// left and still needs covering. Returns whether anything changed.
+  fn force_singletons(&mut self) -> bool {
+    let mut changed = false;
+    for item in 0..self.items.len() {
+      let state = &self.items[item];
+      if !state.active || !state.header_type.is_primary() || state.min == 0 {
+        continue;
+      }
+      if let [option] = self.alive_options(item).take(2).collect::<Vec<_>>()[..] {
+        self.force(option);
+        changed = true;
+      }
+    }
+    changed
+  }
+
+  /// Returns whether choosing `option` would leave some primary item with
+  /// too few compatible options to be covered, so it can't be in any
+  /// solution.
+  fn blocks_an_item(&self, option: usize, blocked: &mut [bool], lost: &mut [u32]) -> bool {
+    let cells = &self.options[option].cells;
+    let mut blocked_options = Vec::new();
+    for &(item, color) in cells {
+      if !self.items[item].active {
+        continue;
+      }
+      for other in self.alive_options(item) {
+        if other != option
+          && !blocked[other]
+          && self.conflicts_on(item, color, self.option_color(other, item))
+        {
+          blocked[other] = true;
+          blocked_options.push(other);
+        }
+      }
+    }
+
+    let mut touched = Vec::new();
+    for &other in &blocked_options {
+      for &(item, _) in &self.options[other].cells {
+        if self.is_primary(item) && self.items[item].active {
+          if lost[item] == 0 {
+            touched.push(item);
+          }
+          lost[item] += 1;
+        }
+      }
+    }
+    // Only the items which lost options can have become uncoverable. If
+    // `option` covers the item itself, the item needs one fewer other option.
+    let dead = touched.iter().any(|&item| {
+      let own = cells.iter().any(|&(i, _)| i == item) as u32;
+      let available = self.alive_options(item).count() as u32 - own - lost[item];
+      available < self.items[item].min.saturating_sub(own)
+    });
+
+    for other in blocked_options {
+      blocked[other] = false;
+    }
+    for item in touched {
+      lost[item] = 0;
+    }
+    dead
+  }
+
+  /// Removes every option which blocks some primary item. Returns whether
+  /// anything changed.
+  fn remove_blocking(&mut self) -> bool {
+    let mut blocked = vec![false; self.options.len()];
+    let mut lost = vec![0; self.items.len()];
+    let mut changed = false;
+    for option in 0..self.options.len() {
+      if self.options[option].alive && self.blocks_an_item(option, &mut blocked, &mut lost) {
+        self.remove(option);
+        changed = true;
+      }
+    }
+    changed
+  }
+
+  /// Returns whether some primary item can no longer be covered often enough,
+  /// so the problem has no solutions.
+  fn infeasible(&self) -> bool {
+    (0..self.items.len()).any(|item| {
+      let state = &self.items[item];
+      state.active
+        && state.header_type.is_primary()
+        && (self.alive_options(item).count() as u32) < state.min
+    })
+  }
+}
+
+/// The result of `Dlx::preprocess`: a smaller problem whose solutions, along
+/// with the forced subsets, are exactly the solutions of the original.
+#[derive(Debug)]
+pub struct Preprocessed<I, N> {
+  dlx: Dlx<I, N>,
+  forced: Vec<N>,
+  removed: Vec<N>,
+}
+
+impl<I, N> Preprocessed<I, N> {
+  /// The reduced problem. Its solutions omit the forced subsets.
+  pub fn dlx(&self) -> &Dlx<I, N> {
+    &self.dlx
+  }
+
+  pub fn dlx_mut(&mut self) -> &mut Dlx<I, N> {
+    &mut self.dlx
+  }
+
+  pub fn into_dlx(self) -> Dlx<I, N> {
+    self.dlx
+  }
+
+  /// The subsets which are part of every solution, in the order they were
+  /// found to be forced.
+  pub fn forced(&self) -> &[N] {
+    &self.forced
+  }
+
+  /// The subsets which aren't part of any solution, and so were left out of
+  /// the reduced problem.
+  pub fn removed(&self) -> &[N] {
+    &self.removed
+  }
+
+  /// Maps a solution of the reduced problem, as subset names, to a solution
+  /// of the original problem by adding the forced subsets before it.
+  pub fn complete_solution(&self, solution: Vec<N>) -> Vec<N>
+  where
+    N: Clone,
+  {
+    self.forced.iter().cloned().chain(solution).collect()
+  }
+
+  /// Iterates over the solutions of the original problem, as the names of
+  /// the subsets in each, by solving the reduced problem.
+  pub fn find_solutions(&mut self) -> impl Iterator<Item = Vec<N>> + '_
+  where
+    N: Clone,
+  {
+    let forced = &self.forced;
+    self
+      .dlx
+      .find_solutions()
+      .with_names()
+      .map(move |solution| forced.iter().cloned().chain(solution).collect())
+  }
+
+  /// Counts the solutions of the original problem.
+  pub fn count_solutions(&mut self) -> u64 {
+    self.dlx.count_solutions()
+  }
+}
+
+impl<I, N> Dlx<I, N>
+where
+  I: Hash + Eq + Clone + Debug,
+  N: Hash + Eq + Clone + Debug,
+{
+  /// Simplifies this problem in the style of Knuth's Algorithm P, repeating
+  /// until nothing changes:
+  ///
+  /// - a subset which is the only way left to cover a primary item is forced,
+  ///   and the subsets it conflicts with are removed, and
+  /// - a subset which would leave some primary item without enough
+  ///   compatible subsets to cover it is removed.
+  ///
+  /// Items the forced subsets leave no room for are dropped. Costs and the
+  /// order of the remaining subsets are kept. If the problem turns out to
+  /// have no solutions, every subset is removed and the reduced problem has
+  /// none either. Otherwise its solutions are those of the reduced problem
+  /// plus the forced subsets (see `Preprocessed::complete_solution`).
+  ///
+  /// This should not be called in the middle of a search.
+  pub fn preprocess(&self) -> Preprocessed<I, N> {
+    let mut pre = Preprocessor::new(self);
+    loop {
+      if pre.infeasible() {
+        // No subset can be part of a solution.
+        for option in 0..pre.options.len() {
+          if pre.options[option].alive {
+            pre.remove(option);
+          }
+        }
+        break;
+      }
+      let forced = pre.force_singletons();
+      let removed = pre.remove_blocking();
+      if !forced && !removed {
+        break;
+      }
+    }
+
+    let mut names = Vec::with_capacity(pre.options.len());
+    for idx in self.headers.len()..self.body.len() {
+      if let Node::Boundary { name, .. } = self.body_node(idx) {
+        names.push(name.as_ref().unwrap());
+      }
+    }
+
+    let items = self.headers[1..(self.headers.len() - 1)]
+      .iter()
+      .zip(&pre.items)
+      .filter(|(_, state)| state.active)
+      .map(|(header, state)| {
+        let header_type = match header.header_type {
+          HeaderType::BoundedPrimary { .. } => HeaderType::BoundedPrimary {
+            min: state.min,
+            max: state.max,
+          },
+          header_type => header_type,
+        };
+        (header.item.clone().unwrap(), header_type)
+      })
+      .collect::<Vec<_>>();
+    let subsets = pre
+      .options
+      .iter()
+      .zip(&names)
+      .filter(|(option, _)| option.alive)
+      .map(|(option, &name)| {
+        let constraints = option
+          .cells
+          .iter()
+          .map(|&(item, color)| {
+            let header = &self.headers[item + 1];
+            let item = header.item.clone().unwrap();
+            match color {
+              Some(color) => Constraint::Secondary(ColorItem::new(item, color)),
+              None if header.is_primary() => Constraint::Primary(item),
+              None => Constraint::SecondaryUncolored(item),
+            }
+          })
+          .collect::<Vec<_>>();
+        (name.clone(), option.cost, constraints)
+      })
+      .collect::<Vec<_>>();
+
+    Preprocessed {
+      dlx: Self::construct(items, subsets)
+        .unwrap_or_else(|err| panic!("Preprocessing produced a malformed problem: {err}")),
+      forced: pre
+        .forced
+        .iter()
+        .map(|&option| names[option].clone())
+        .collect(),
+      removed: pre
+        .removed
+        .iter()
+        .map(|&option| names[option].clone())
+        .collect(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use googletest::prelude::*;
+  use itertools::Itertools;
+
+  use crate::{
+    dlx::test::random_problem, ColorItem, Constraint, Dlx, DlxIteratorWithNames, HeaderType,
+  };
+
+  fn sorted<N: Ord>(solutions: impl Iterator<Item = Vec<N>>) -> Vec<Vec<N>> {
+    solutions
+      .map(|solution| solution.into_iter().sorted().collect_vec())
+      .sorted()
+      .collect()
+  }
+
+  #[gtest]
+  fn test_preprocess_keeps_solutions() {
+    for seed in 0..300 {
+      let (items, subsets) = random_problem(seed);
+      let mut dlx = Dlx::new(items, subsets);
+      let expected = sorted(dlx.find_solutions().with_names());
+
+      let mut preprocessed = dlx.preprocess();
+      expect_eq!(
+        sorted(preprocessed.find_solutions()),
+        expected,
+        "seed {seed}"
+      );
+      expect_eq!(
+        preprocessed.count_solutions(),
+        expected.len() as u64,
+        "seed {seed}"
+      );
+      expect_eq!(
+        preprocessed.dlx().subsets().count()
+          + preprocessed.forced().len()
+          + preprocessed.removed().len(),
+        dlx.subsets().count(),
+        "seed {seed}"
+      );
+    }
+  }
+
+  #[gtest]
+  fn test_preprocess_forces_and_removes() {
+    let dlx = Dlx::new(
+      vec![
+        ('p', HeaderType::Primary),
+        ('q', HeaderType::Primary),
+        ('r', HeaderType::Primary),
+        ('t', HeaderType::Primary),
+        ('s', HeaderType::Secondary),
+      ],
+      vec![
+        (0, vec![Constraint::Primary('p'), 'q'.into()]),
+        (1, vec!['p'.into(), 'r'.into()]),
+        (2, vec!['q'.into()]),
+        (
+          3,
+          vec![Constraint::Primary('r'), ColorItem::new('s', 2).into()],
+        ),
+        (4, vec![ColorItem::new('s', 1).into()]),
+        (5, vec!['t'.into(), 'q'.into()]),
+      ],
+    );
+
+    let mut preprocessed = dlx.preprocess();
+    expect_that!(preprocessed.forced(), elements_are![eq(&5), eq(&1)]);
+    expect_that!(
+      preprocessed.removed(),
+      elements_are![eq(&0), eq(&2), eq(&3)]
+    );
+    expect_that!(
+      preprocessed.dlx().items().collect_vec(),
+      elements_are![eq(&(&'s', HeaderType::Secondary))]
+    );
+    expect_that!(
+      preprocessed
+        .dlx()
+        .subsets()
+        .map(|(&name, _)| name)
+        .collect_vec(),
+      elements_are![eq(&4)]
+    );
+    expect_eq!(preprocessed.complete_solution(vec![4]), vec![5, 1, 4]);
+    // Subsets without primary items are never chosen by the search.
+    expect_that!(
+      preprocessed.find_solutions().collect_vec(),
+      elements_are![eq(&vec![5, 1])]
+    );
+  }
+
+  #[gtest]
+  fn test_preprocess_no_solutions() {
+    let dlx = Dlx::new(
+      vec![
+        ('p', HeaderType::Primary),
+        ('q', HeaderType::Primary),
+        ('r', HeaderType::Primary),
+      ],
+      vec![
+        (0, vec!['p', 'q']),
+        (1, vec!['q', 'r']),
+        (2, vec!['p', 'r']),
+      ],
+    );
+
+    let mut preprocessed = dlx.preprocess();
+    expect_that!(preprocessed.forced(), empty());
+    expect_that!(preprocessed.removed(), len(eq(3)));
+    expect_eq!(preprocessed.count_solutions(), 0);
+  }
+}
@@ -0,0 +1,143 @@
+use rand::{seq::SliceRandom, Rng};
+
+use super::Dlx;
+
+impl<I, N> Dlx<I, N> {
+  fn item_list(&self, top: usize) -> Vec<usize> {
+    let mut nodes = Vec::with_capacity(self.body_header(top).len());
+    let mut p = self.body_header(top).next();
+    while p != top {
+      nodes.push(p);
+      p = self.body_node(p).next();
+    }
+    nodes
+  }
+
+  /// Replaces the list of the item `top` with `nodes`, in order.
+  fn relink_item_list(&mut self, top: usize, nodes: &[usize]) {
+    let mut prev = top;
+    for &p in nodes {
+      self.node_mut(prev).set_next(p);
+      self.node_mut(p).set_prev(prev);
+      prev = p;
+    }
+    self.node_mut(prev).set_next(top);
+    self.node_mut(top).set_prev(prev);
+  }
+
+  /// Shuffles the order in which the subsets of each primary item are tried.
+  /// This must be undone with `unshuffle_item_lists` before any subsets are
+  /// restored which were hidden before the shuffle.
+  pub(super) fn shuffle_item_lists<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+    for top in 1..=self.num_primary_items {
+      let mut nodes = self.item_list(top);
+      nodes.shuffle(rng);
+      self.relink_item_list(top, &nodes);
+    }
+  }
+
+  /// Reverts `shuffle_item_lists`, by putting every primary item's list back
+  /// in order of node index, which is the order the lists are built in.
+  pub(super) fn unshuffle_item_lists(&mut self) {
+    for top in 1..=self.num_primary_items {
+      let mut nodes = self.item_list(top);
+      nodes.sort_unstable();
+      self.relink_item_list(top, &nodes);
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use googletest::prelude::*;
+  use itertools::Itertools;
+
+  use crate::{
+    dlx::test::{random_problem, singletons_and_pairs},
+    Dlx, DlxIteratorWithNames, SearchOptions, StepwiseDlxIterResult,
+  };
+
+  #[gtest]
+  fn test_seeded_search_is_reproducible() {
+    let mut dlx = singletons_and_pairs();
+    let search = |dlx: &mut Dlx<u32, usize>, seed| {
+      dlx
+        .find_solutions_with(SearchOptions::new().with_seed(seed))
+        .with_names()
+        .take(20)
+        .collect_vec()
+    };
+
+    let first = search(&mut dlx, 1);
+    expect_eq!(search(&mut dlx, 1), first);
+    expect_ne!(search(&mut dlx, 2), first);
+    expect_ne!(
+      dlx.find_solutions().with_names().take(20).collect_vec(),
+      first
+    );
+  }
+
+  #[gtest]
+  fn test_seeded_search_finds_all_solutions() {
+    for seed in 0..50 {
+      let (items, subsets) = random_problem(seed);
+      let mut dlx = Dlx::new(items, subsets);
+      let sorted = |solution: Vec<u32>| solution.into_iter().sorted().collect_vec();
+
+      let expected = dlx
+        .find_solutions()
+        .with_names()
+        .map(sorted)
+        .sorted()
+        .collect_vec();
+      let expected_steps = dlx
+        .find_solutions_stepwise()
+        .map(StepwiseDlxIterResult::take_result)
+        .collect_vec();
+      expect_eq!(
+        dlx
+          .find_solutions_with(SearchOptions::new().with_seed(seed))
+          .with_names()
+          .map(sorted)
+          .sorted()
+          .collect_vec(),
+        expected,
+        "seed {seed}"
+      );
+
+      // Stopping a randomized search early must restore the original order.
+      expect_that!(
+        dlx
+          .find_solutions_with(SearchOptions::new().with_seed(seed).with_node_budget(5))
+          .count(),
+        le(5)
+      );
+      expect_eq!(
+        dlx
+          .find_solutions_stepwise()
+          .map(StepwiseDlxIterResult::take_result)
+          .collect_vec(),
+        expected_steps,
+        "seed {seed}"
+      );
+    }
+  }
+
+  #[gtest]
+  fn test_seeded_search_with_givens() {
+    let mut dlx = singletons_and_pairs();
+    let mut with_givens = dlx.with_givens(&[0, 15]).unwrap();
+    let expected = with_givens.count_solutions();
+
+    expect_eq!(
+      with_givens
+        .find_solutions_with(SearchOptions::new().with_seed(3))
+        .count() as u64,
+      expected
+    );
+    expect_eq!(with_givens.count_solutions(), expected);
+    drop(with_givens);
+
+    expect_eq!(dlx.count_solutions(), 764);
+  }
+}
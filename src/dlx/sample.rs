@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+
+use rand::Rng;
+
+use super::{
+  ChooseNextItemResult, Dlx, DlxExplorer, DlxStepResult, ExploreNextChoiceResult, SearchOptions,
+  SearchStatus,
+};
+
+/// Subtrees with at most this many nodes have their solutions counted
+/// exactly when sampling. Larger subtrees are estimated.
+const EXACT_COUNT_NODE_BUDGET: u64 = 100_000;
+
+/// The number of random probes used to estimate the number of solutions in a
+/// subtree which is too large to count exactly.
+const ESTIMATE_PROBES: usize = 64;
+
+/// How many times to restart the search for a single sample after following
+/// an estimate into a subtree without any solutions.
+const MAX_ATTEMPTS_PER_SAMPLE: usize = 1000;
+
+/// The number of solutions below a node of the search tree.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Weight {
+  Exact(u64),
+  Estimated(f64),
+}
+
+impl Weight {
+  fn value(self) -> f64 {
+    match self {
+      Self::Exact(count) => count as f64,
+      Self::Estimated(estimate) => estimate,
+    }
+  }
+}
+
+impl<I, N> Dlx<I, N> {
+  /// Returns the number of solutions of the problem in its current state,
+  /// which is exact if it can be counted within `exact_count_budget` nodes,
+  /// and otherwise estimated.
+  fn subtree_weight<R: Rng + ?Sized>(&mut self, rng: &mut R, exact_count_budget: u64) -> Weight {
+    let mut explorer = DlxExplorer::with_options(
+      &mut *self,
+      SearchOptions::new().with_node_budget(exact_count_budget),
+    );
+    let mut count = 0u64;
+    loop {
+      match explorer.step() {
+        DlxStepResult::Continue => {}
+        DlxStepResult::FoundSolution(_) => count += 1,
+        DlxStepResult::Done => break,
+      }
+    }
+    if explorer.status() == SearchStatus::Exhausted {
+      return Weight::Exact(count);
+    }
+    drop(explorer);
+
+    Weight::Estimated(self.estimate_search(ESTIMATE_PROBES, rng).solutions())
+  }
+
+  /// Returns the solution with index `n` (from 0) of the problem in its
+  /// current state, in the order the search finds them.
+  fn nth_solution(&mut self, n: u64) -> Option<Vec<usize>> {
+    let mut explorer = DlxExplorer::new(&mut *self);
+    let mut count = 0u64;
+    loop {
+      match explorer.step() {
+        DlxStepResult::Continue => {}
+        DlxStepResult::FoundSolution(solution) if count == n => return Some(solution.clone()),
+        DlxStepResult::FoundSolution(_) => count += 1,
+        DlxStepResult::Done => return None,
+      }
+    }
+  }
+
+  /// Walks down the search tree, choosing each branch with probability
+  /// proportional to the number of solutions below it. Returns None if the
+  /// walk ended up in a subtree without solutions, which can only happen if
+  /// some of the counts were estimated.
+  ///
+  /// Once the walk enters a subtree whose solutions were counted exactly, it
+  /// picks one of them uniformly at random instead of weighing the subtree
+  /// again at every level. Exact counts are kept in `exact_counts`, keyed by
+  /// the path to the subtree, so that later samples can reuse them.
+  fn sample_solution<R: Rng + ?Sized>(
+    &mut self,
+    rng: &mut R,
+    exact_count_budget: u64,
+    exact_counts: &mut HashMap<Vec<usize>, u64>,
+  ) -> Option<Vec<usize>> {
+    let mut explorer = DlxExplorer::new(&mut *self);
+    let mut weights = Vec::new();
+    loop {
+      let depth = explorer.partial_solution().len();
+      if let ChooseNextItemResult::FoundSolution = explorer.choose_next_item() {
+        return Some(explorer.partial_solution().clone());
+      }
+
+      // Weigh each choice for the item by the number of solutions below it.
+      explorer.floor = depth;
+      weights.clear();
+      while let ExploreNextChoiceResult::Continue = explorer.explore_next_choice() {
+        let weight = match exact_counts.get(explorer.partial_solution()) {
+          Some(&count) => Weight::Exact(count),
+          None => {
+            let weight = explorer.dlx.subtree_weight(rng, exact_count_budget);
+            if let Weight::Exact(count) = weight {
+              exact_counts.insert(explorer.partial_solution().clone(), count);
+            }
+            weight
+          }
+        };
+        weights.push(weight);
+      }
+
+      let total: f64 = weights.iter().map(|weight| weight.value()).sum();
+      let choice = if total > 0. {
+        let mut target = rng.random_range(0. ..total);
+        weights
+          .iter()
+          .position(|weight| {
+            target -= weight.value();
+            target < 0.
+          })
+          .unwrap_or(weights.len() - 1)
+      } else if weights
+        .iter()
+        .any(|weight| matches!(weight, Weight::Estimated(_)))
+      {
+        // The estimates found no solutions, which doesn't mean there aren't
+        // any, so pick blindly.
+        rng.random_range(0..weights.len())
+      } else {
+        return None;
+      };
+
+      // Return to the chosen branch.
+      let ChooseNextItemResult::Continue = explorer.choose_next_item() else {
+        dlx_unreachable!("Item to branch on disappeared at depth {depth}");
+      };
+      for _ in 0..=choice {
+        let ExploreNextChoiceResult::Continue = explorer.explore_next_choice() else {
+          dlx_unreachable!("Choice {choice} disappeared at depth {depth}");
+        };
+      }
+
+      if let Weight::Exact(count) = weights[choice] {
+        if count == 0 {
+          return None;
+        }
+        let Some(rest) = explorer.dlx.nth_solution(rng.random_range(0..count)) else {
+          dlx_unreachable!("Subtree at depth {depth} has fewer than {count} solutions");
+        };
+        let mut solution = explorer.partial_solution().clone();
+        solution.extend(rest);
+        return Some(solution);
+      }
+    }
+  }
+
+  fn sample_solutions_with_budget<R: Rng + ?Sized>(
+    &mut self,
+    rng: &mut R,
+    n: usize,
+    exact_count_budget: u64,
+  ) -> Vec<Vec<usize>> {
+    let mut exact_counts = HashMap::new();
+    let mut samples = Vec::with_capacity(n);
+    'samples: while samples.len() < n {
+      for _ in 0..MAX_ATTEMPTS_PER_SAMPLE {
+        if let Some(solution) = self.sample_solution(rng, exact_count_budget, &mut exact_counts) {
+          samples.push(solution);
+          continue 'samples;
+        }
+        if self.subtree_weight(rng, exact_count_budget) == Weight::Exact(0) {
+          // There are no solutions at all.
+          break;
+        }
+      }
+      break;
+    }
+    samples
+  }
+}
+
+impl<I, N> Dlx<I, N>
+where
+  N: Clone,
+{
+  /// Draws `n` random solutions (with replacement), returning the names of
+  /// the subsets in each.
+  ///
+  /// Each sample walks down the search tree, choosing every branch with
+  /// probability proportional to the number of solutions below it. Subtrees
+  /// of up to 100,000 nodes are counted exactly, so if every choice at the
+  /// top of the tree leads to a subtree that small, the samples are exactly
+  /// uniform. Larger subtrees are weighed by a Monte Carlo estimate (see
+  /// `Dlx::estimate_search`), which biases the samples towards solutions in
+  /// subtrees whose size was overestimated, so the samples are only roughly
+  /// uniform, and the bias grows with the variance of the estimates.
+  ///
+  /// Once a sample reaches a subtree that was counted exactly, it picks one
+  /// of the subtree's solutions uniformly instead of weighing the subtree
+  /// again further down. Exact counts are also shared between the samples of
+  /// one call, so after the first sample, a sample costs at most one
+  /// enumeration of up to 100,000 nodes, plus fresh estimates of any larger
+  /// subtrees it meets on the way down.
+  ///
+  /// Returns fewer than `n` samples if there are no solutions, or if the
+  /// estimates repeatedly lead the search into subtrees without any. The
+  /// `Dlx` is left unmodified afterwards.
+  pub fn sample_solutions<R: Rng + ?Sized>(&mut self, rng: &mut R, n: usize) -> Vec<Vec<N>> {
+    self
+      .sample_solutions_with_budget(rng, n, EXACT_COUNT_NODE_BUDGET)
+      .iter()
+      .map(|solution| self.solution_names(solution))
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::collections::HashMap;
+
+  use googletest::prelude::*;
+  use itertools::Itertools;
+  use rand::{rngs::StdRng, SeedableRng};
+
+  use crate::{Dlx, DlxIteratorWithNames, HeaderType};
+
+  /// Covers 5 items with singletons and pairs. Choosing the singleton for the
+  /// first item leaves 10 solutions, while each pair leaves 4.
+  fn lopsided() -> Dlx<u32, usize> {
+    let subsets = (0..5)
+      .map(|item| vec![item])
+      .chain((0..5).tuple_combinations().map(|(a, b)| vec![a, b]))
+      .enumerate()
+      .collect_vec();
+    Dlx::new((0..5).map(|item| (item, HeaderType::Primary)), subsets)
+  }
+
+  fn sorted(solution: &[usize]) -> Vec<usize> {
+    solution.iter().copied().sorted().collect()
+  }
+
+  #[gtest]
+  fn test_sample_is_uniform() {
+    let mut dlx = lopsided();
+    let solutions = dlx
+      .find_solutions()
+      .with_names()
+      .map(|solution| sorted(&solution))
+      .collect_vec();
+    expect_eq!(solutions.len(), 26);
+
+    let mut rng = StdRng::seed_from_u64(1);
+    let samples = dlx.sample_solutions(&mut rng, 26 * 200);
+    expect_eq!(samples.len(), 26 * 200);
+    let counts = samples.iter().map(|solution| sorted(solution)).counts();
+    expect_eq!(counts.len(), 26);
+    for solution in &solutions {
+      expect_that!(counts[solution], ge(140).and(le(260)), "{solution:?}");
+    }
+
+    // Sampling must leave the problem unmodified.
+    expect_eq!(dlx.count_solutions(), 26);
+  }
+
+  #[gtest]
+  fn test_sample_with_estimates() {
+    let mut dlx = lopsided();
+    let solutions = dlx
+      .find_solutions()
+      .with_names()
+      .map(|solution| sorted(&solution))
+      .collect_vec();
+
+    let mut rng = StdRng::seed_from_u64(1);
+    let samples = dlx.sample_solutions_with_budget(&mut rng, 300, 1);
+    expect_eq!(samples.len(), 300);
+    let counts: HashMap<_, _> = samples
+      .iter()
+      .map(|solution| sorted(&dlx.solution_names(solution)))
+      .counts();
+    for solution in counts.keys() {
+      expect_that!(solutions, contains(eq(solution)));
+    }
+    expect_eq!(counts.len(), 26);
+    expect_eq!(dlx.count_solutions(), 26);
+  }
+
+  #[gtest]
+  fn test_sample_no_solutions() {
+    let mut dlx = Dlx::new(
+      vec![('p', HeaderType::Primary), ('q', HeaderType::Primary)],
+      vec![(0, vec!['p']), (1, vec!['p'])],
+    );
+    let mut rng = StdRng::seed_from_u64(1);
+    expect_that!(dlx.sample_solutions(&mut rng, 3), empty());
+    expect_that!(dlx.sample_solutions_with_budget(&mut rng, 3, 1), empty());
+  }
+}
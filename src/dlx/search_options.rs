@@ -0,0 +1,181 @@
+use std::{
+  fmt::{self, Debug, Formatter},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+  },
+  time::{Duration, Instant},
+};
+
+use super::ItemChooser;
+
+/// How many nodes are visited between checks of the search deadline, to keep
+/// calls to `Instant::now` out of the hot loop.
+const DEADLINE_CHECK_INTERVAL: u64 = 64;
+
+/// Options controlling a search over items of type `I`, passed to
+/// `Dlx::find_solutions_with` and friends. The default options search without
+/// any limits, using the MRV heuristic.
+pub struct SearchOptions<I> {
+  pub(super) node_budget: Option<u64>,
+  pub(super) deadline: Option<Instant>,
+  pub(super) collect_stats: bool,
+  pub(super) chooser: Option<Box<dyn ItemChooser<I>>>,
+  pub(super) seed: Option<u64>,
+  pub(super) cancellation: Option<CancellationToken>,
+}
+
+impl<I> SearchOptions<I> {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Stops the search after visiting `node_budget` nodes of the search tree,
+  /// where each step of a stepwise iterator visits one node.
+  pub fn with_node_budget(mut self, node_budget: u64) -> Self {
+    self.node_budget = Some(node_budget);
+    self
+  }
+
+  /// Stops the search once `deadline` has passed. The deadline is only
+  /// checked every few nodes, so the search may run slightly past it.
+  pub fn with_deadline(mut self, deadline: Instant) -> Self {
+    self.deadline = Some(deadline);
+    self
+  }
+
+  /// Stops the search once `timeout` has elapsed from now.
+  pub fn with_timeout(self, timeout: Duration) -> Self {
+    self.with_deadline(Instant::now() + timeout)
+  }
+
+  /// Collects `SearchStats` during the search, which can be read with
+  /// `DlxIterator::stats`.
+  pub fn with_stats(mut self) -> Self {
+    self.collect_stats = true;
+    self
+  }
+
+  /// Uses `chooser` to pick the item to branch on at each node of the search
+  /// tree, instead of the built-in MRV heuristic.
+  pub fn with_chooser(mut self, chooser: impl ItemChooser<I> + 'static) -> Self {
+    self.chooser = Some(Box::new(chooser));
+    self
+  }
+
+  /// Randomizes the search: the subsets of each item are tried in a random
+  /// order, and unless a chooser is given, ties between items with the
+  /// fewest remaining choices are broken at random (see `RandomizedMrv`).
+  /// The same seed always gives the same search.
+  pub fn with_seed(mut self, seed: u64) -> Self {
+    self.seed = Some(seed);
+    self
+  }
+
+  /// Stops the search once `token` is cancelled, which can be done from
+  /// another thread. The token is checked before every node of the search
+  /// tree.
+  pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+    self.cancellation = Some(token);
+    self
+  }
+}
+
+impl<I> SearchOptions<I> {
+  /// Returns the limit which a search that has visited `nodes` nodes has
+  /// hit, if any.
+  pub(super) fn exceeded_limit(&self, nodes: u64) -> Option<SearchStatus> {
+    if self
+      .node_budget
+      .is_some_and(|node_budget| nodes >= node_budget)
+    {
+      return Some(SearchStatus::NodeBudgetExceeded);
+    }
+    if self
+      .cancellation
+      .as_ref()
+      .is_some_and(CancellationToken::is_cancelled)
+    {
+      return Some(SearchStatus::Cancelled);
+    }
+    if nodes.is_multiple_of(DEADLINE_CHECK_INTERVAL)
+      && self
+        .deadline
+        .is_some_and(|deadline| Instant::now() >= deadline)
+    {
+      return Some(SearchStatus::DeadlineExceeded);
+    }
+    None
+  }
+}
+
+impl<I> Default for SearchOptions<I> {
+  fn default() -> Self {
+    Self {
+      node_budget: None,
+      deadline: None,
+      collect_stats: false,
+      chooser: None,
+      seed: None,
+      cancellation: None,
+    }
+  }
+}
+
+impl<I> Debug for SearchOptions<I> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.debug_struct("SearchOptions")
+      .field("node_budget", &self.node_budget)
+      .field("deadline", &self.deadline)
+      .field("collect_stats", &self.collect_stats)
+      .field("chooser", &self.chooser.as_ref().map(|_| "..."))
+      .field("seed", &self.seed)
+      .field("cancellation", &self.cancellation)
+      .finish()
+  }
+}
+
+/// The state of a search, as reported by `DlxIterator::status`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchStatus {
+  /// The search may still find more solutions.
+  Running,
+  /// The whole search tree was explored, so every solution has been found.
+  Exhausted,
+  /// The search was stopped by the node budget in `SearchOptions`.
+  NodeBudgetExceeded,
+  /// The search was stopped by the deadline in `SearchOptions`.
+  DeadlineExceeded,
+  /// The search was stopped by the cancellation token in `SearchOptions`.
+  Cancelled,
+}
+
+/// A flag for stopping a search from another thread, passed to
+/// `SearchOptions::with_cancellation`. Clones of a token share the same flag,
+/// so cancelling any of them cancels them all.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+  cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Cancels every search using this token. This can't be undone.
+  pub fn cancel(&self) {
+    self.cancelled.store(true, Ordering::Relaxed);
+  }
+
+  pub fn is_cancelled(&self) -> bool {
+    self.cancelled.load(Ordering::Relaxed)
+  }
+}
+
+/// Wraps an existing flag, so setting it to true cancels the search.
+impl From<Arc<AtomicBool>> for CancellationToken {
+  fn from(cancelled: Arc<AtomicBool>) -> Self {
+    Self { cancelled }
+  }
+}
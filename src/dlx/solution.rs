@@ -0,0 +1,231 @@
+use std::{
+  fmt::{self, Debug, Formatter},
+  iter,
+  ops::ControlFlow,
+};
+
+use super::{Dlx, DlxExplorer, DlxStepResult, Node, NodeType};
+
+/// A solution lent to the callback of `Dlx::solve_with`. Nothing is copied
+/// out of the `Dlx` until asked for, so inspecting and discarding solutions
+/// doesn't allocate.
+pub struct Solution<'a, I, N> {
+  dlx: &'a Dlx<I, N>,
+  /// The partial solution of the search, which also contains the headers of
+  /// items with multiplicities which were left partially covered.
+  nodes: &'a [usize],
+}
+
+impl<'a, I, N> Solution<'a, I, N> {
+  /// Iterates over the subsets chosen in this solution, in the order they
+  /// were chosen.
+  pub fn subsets(&self) -> impl Iterator<Item = SolutionSubset<'a, I, N>> + '_ {
+    let dlx = self.dlx;
+    self.nodes.iter().filter_map(move |&p| {
+      matches!(
+        dlx.node(p),
+        Node::Normal {
+          node_type: NodeType::Body { .. },
+          ..
+        }
+      )
+      .then_some(SolutionSubset { dlx, node: p })
+    })
+  }
+
+  /// Iterates over the names of the subsets chosen in this solution.
+  pub fn names(&self) -> impl Iterator<Item = &'a N> + '_ {
+    self.subsets().map(|subset| subset.name())
+  }
+
+  /// The number of subsets chosen in this solution.
+  pub fn len(&self) -> usize {
+    self.subsets().count()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.subsets().next().is_none()
+  }
+
+  /// Returns the color assigned to the secondary item `item`, or None if no
+  /// chosen subset colors it.
+  pub fn color(&self, item: &I) -> Option<u32>
+  where
+    I: PartialEq,
+  {
+    self
+      .subsets()
+      .flat_map(|subset| subset.nodes())
+      .find_map(|p| {
+        let color = self.dlx.body_node(p).color()?;
+        (self.dlx.item_for_node(p) == item).then_some(color)
+      })
+  }
+}
+
+impl<I, N> Debug for Solution<'_, I, N>
+where
+  N: Debug,
+{
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.debug_list().entries(self.names()).finish()
+  }
+}
+
+/// One of the subsets chosen in a `Solution`.
+pub struct SolutionSubset<'a, I, N> {
+  dlx: &'a Dlx<I, N>,
+  /// The node through which the subset was chosen.
+  node: usize,
+}
+
+impl<'a, I, N> SolutionSubset<'a, I, N> {
+  fn nodes(&self) -> impl Iterator<Item = usize> + 'a {
+    iter::once(self.node).chain(self.dlx.iterate_items(self.node))
+  }
+
+  pub fn name(&self) -> &'a N {
+    self.dlx.subset_name(self.node)
+  }
+
+  pub fn cost(&self) -> u64 {
+    self.dlx.subset_cost(self.node)
+  }
+
+  /// Iterates over the items of this subset, starting with the item the
+  /// search chose it for. Use `Solution::color` for the colors of secondary
+  /// items.
+  pub fn items(&self) -> impl Iterator<Item = &'a I> + 'a {
+    let dlx = self.dlx;
+    self.nodes().map(move |p| dlx.item_for_node(p))
+  }
+}
+
+impl<I, N> Debug for SolutionSubset<'_, I, N>
+where
+  N: Debug,
+{
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.debug_tuple("SolutionSubset").field(self.name()).finish()
+  }
+}
+
+impl<I, N> Dlx<I, N> {
+  /// Searches for solutions, calling `f` with a view of each one as it is
+  /// found. Unlike `find_solutions`, this doesn't allocate anything per
+  /// solution. The search stops early if `f` returns `ControlFlow::Break`,
+  /// which is passed on as the result.
+  ///
+  /// The `Dlx` is left unmodified afterwards.
+  pub fn solve_with<F>(&mut self, mut f: F) -> ControlFlow<()>
+  where
+    F: FnMut(&Solution<'_, I, N>) -> ControlFlow<()>,
+  {
+    let mut explorer = DlxExplorer::new(self);
+    loop {
+      match explorer.step() {
+        DlxStepResult::Continue => {}
+        DlxStepResult::FoundSolution(_) => {
+          let solution = Solution {
+            dlx: explorer.dlx(),
+            nodes: explorer.partial_solution(),
+          };
+          f(&solution)?;
+        }
+        DlxStepResult::Done => return ControlFlow::Continue(()),
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::ops::ControlFlow;
+
+  use googletest::prelude::*;
+  use itertools::Itertools;
+
+  use crate::{
+    dlx::test::{random_problem, singletons_and_pairs},
+    ColorItem, Constraint, Dlx, DlxIteratorWithNames, HeaderType,
+  };
+
+  #[gtest]
+  fn test_solve_with_matches_find_solutions() {
+    for seed in 0..50 {
+      let (items, subsets) = random_problem(seed);
+      let mut dlx = Dlx::new(items, subsets);
+      let expected = dlx.find_solutions().with_names().collect_vec();
+
+      let mut solutions = vec![];
+      let result = dlx.solve_with(|solution| {
+        expect_eq!(solution.len(), solution.names().count());
+        expect_eq!(solution.is_empty(), solution.names().next().is_none());
+        solutions.push(solution.names().copied().collect_vec());
+        ControlFlow::Continue(())
+      });
+      expect_eq!(result, ControlFlow::Continue(()));
+      expect_eq!(solutions, expected, "seed {seed}");
+    }
+  }
+
+  #[gtest]
+  fn test_solve_with_break() {
+    let mut dlx = singletons_and_pairs();
+    let mut seen = 0;
+    let result = dlx.solve_with(|_| {
+      seen += 1;
+      if seen == 3 {
+        ControlFlow::Break(())
+      } else {
+        ControlFlow::Continue(())
+      }
+    });
+    expect_eq!(result, ControlFlow::Break(()));
+    expect_eq!(seen, 3);
+
+    // Stopping early must leave the problem unmodified.
+    expect_eq!(dlx.count_solutions(), 764);
+  }
+
+  #[gtest]
+  fn test_solution_items_and_colors() {
+    let mut dlx = Dlx::new(
+      vec![
+        ('p', HeaderType::Primary),
+        ('q', HeaderType::Primary),
+        ('a', HeaderType::Secondary),
+        ('b', HeaderType::Secondary),
+      ],
+      vec![
+        (
+          0,
+          vec![Constraint::Primary('p'), ColorItem::new('a', 1).into()],
+        ),
+        (1, vec!['p'.into(), ColorItem::new('a', 2).into()]),
+        (2, vec!['q'.into(), ColorItem::new('a', 3).into()]),
+        (3, vec!['q'.into(), ColorItem::new('a', 1).into()]),
+      ],
+    );
+
+    let mut solutions = 0;
+    let _ = dlx.solve_with(|solution| {
+      solutions += 1;
+      expect_that!(
+        solution.names().copied().sorted().collect_vec(),
+        elements_are![eq(&0), eq(&3)]
+      );
+      for subset in solution.subsets() {
+        let primary = if *subset.name() == 0 { 'p' } else { 'q' };
+        expect_that!(
+          subset.items().copied().sorted().collect_vec(),
+          elements_are![eq(&'a'), eq(&primary)]
+        );
+      }
+      expect_eq!(solution.color(&'a'), Some(1));
+      expect_eq!(solution.color(&'b'), None);
+      ControlFlow::Continue(())
+    });
+    expect_eq!(solutions, 1);
+  }
+}
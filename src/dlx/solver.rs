@@ -0,0 +1,139 @@
+use std::{error::Error, fmt::Debug, hash::Hash, marker::PhantomData};
+
+use super::{
+  Constraint, DancingCells, DancingCellsError, DancingCellsSolutions, Dlx, DlxError, DlxIterator,
+  DlxIteratorWithNames, HeaderType, SearchOptions, SearchStats, SearchStatus,
+};
+
+/// The solutions found by an `ExactCoverSolver`, which like a `DlxIterator`
+/// can report why the search stopped, and how much work it took.
+pub trait SolverIterator<N>: Iterator<Item = Vec<N>> {
+  /// Reports whether the search is still running, or why it stopped.
+  fn status(&self) -> SearchStatus;
+
+  /// Returns the statistics collected so far, if the search was started with
+  /// `SearchOptions::with_stats`.
+  fn stats(&self) -> Option<&SearchStats>;
+}
+
+/// The operations shared by the solver backends, `Dlx` and `DancingCells`, so
+/// that code can be written once and benchmarked against both.
+pub trait ExactCoverSolver<I, N>: Sized {
+  /// The error returned when the problem can't be built.
+  type Error: Error;
+
+  /// Builds the problem from the same input as `Dlx::try_new`.
+  fn try_new<U, S, C, D>(items: U, subsets: S) -> Result<Self, Self::Error>
+  where
+    U: IntoIterator<Item = (I, HeaderType)>,
+    S: IntoIterator<Item = (N, C)>,
+    C: IntoIterator<Item = D>,
+    D: Into<Constraint<I>>;
+
+  /// Iterates over the solutions, as the names of the subsets in each, like
+  /// `Dlx::find_solutions().with_names()`. Backends may find the solutions
+  /// in different orders.
+  fn solutions(&mut self) -> impl SolverIterator<N> + '_ {
+    self.solutions_with(SearchOptions::default())
+  }
+
+  /// Like `solutions`, but searching with `options`. Every backend supports
+  /// all of the options, though a seeded search finds the solutions in a
+  /// different order on each backend.
+  fn solutions_with(&mut self, options: SearchOptions<I>) -> impl SolverIterator<N> + '_;
+
+  /// Counts the solutions, without materializing any of them.
+  fn count_solutions(&mut self) -> u64;
+}
+
+/// Adapts the solutions of a `Dlx` search to `SolverIterator`.
+struct DlxSolutions<T, I>(T, PhantomData<I>);
+
+impl<T, I, N> Iterator for DlxSolutions<T, I>
+where
+  T: Iterator<Item = Vec<N>>,
+{
+  type Item = Vec<N>;
+
+  fn next(&mut self) -> Option<Vec<N>> {
+    self.0.next()
+  }
+}
+
+impl<T, I, N> SolverIterator<N> for DlxSolutions<T, I>
+where
+  T: DlxIterator<I, N, Vec<N>>,
+{
+  fn status(&self) -> SearchStatus {
+    self.0.status()
+  }
+
+  fn stats(&self) -> Option<&SearchStats> {
+    self.0.stats()
+  }
+}
+
+impl<I, N> SolverIterator<N> for DancingCellsSolutions<'_, I, N>
+where
+  N: Clone,
+{
+  fn status(&self) -> SearchStatus {
+    DancingCellsSolutions::status(self)
+  }
+
+  fn stats(&self) -> Option<&SearchStats> {
+    DancingCellsSolutions::stats(self)
+  }
+}
+
+impl<I, N> ExactCoverSolver<I, N> for Dlx<I, N>
+where
+  I: Hash + Eq + Clone + Debug,
+  N: Hash + Eq + Clone + Debug,
+{
+  type Error = DlxError<I, N>;
+
+  fn try_new<U, S, C, D>(items: U, subsets: S) -> Result<Self, DlxError<I, N>>
+  where
+    U: IntoIterator<Item = (I, HeaderType)>,
+    S: IntoIterator<Item = (N, C)>,
+    C: IntoIterator<Item = D>,
+    D: Into<Constraint<I>>,
+  {
+    Dlx::try_new(items, subsets)
+  }
+
+  fn solutions_with(&mut self, options: SearchOptions<I>) -> impl SolverIterator<N> + '_ {
+    DlxSolutions(self.find_solutions_with(options).with_names(), PhantomData)
+  }
+
+  fn count_solutions(&mut self) -> u64 {
+    Dlx::count_solutions(self)
+  }
+}
+
+impl<I, N> ExactCoverSolver<I, N> for DancingCells<I, N>
+where
+  I: Hash + Eq + Clone + Debug,
+  N: Hash + Eq + Clone + Debug,
+{
+  type Error = DancingCellsError<I, N>;
+
+  fn try_new<U, S, C, D>(items: U, subsets: S) -> Result<Self, DancingCellsError<I, N>>
+  where
+    U: IntoIterator<Item = (I, HeaderType)>,
+    S: IntoIterator<Item = (N, C)>,
+    C: IntoIterator<Item = D>,
+    D: Into<Constraint<I>>,
+  {
+    DancingCells::try_new(items, subsets)
+  }
+
+  fn solutions_with(&mut self, options: SearchOptions<I>) -> impl SolverIterator<N> + '_ {
+    self.find_solutions_with(options)
+  }
+
+  fn count_solutions(&mut self) -> u64 {
+    DancingCells::count_solutions(self)
+  }
+}
@@ -0,0 +1,66 @@
+use std::collections::BTreeMap;
+
+/// Counters describing the effort spent on a search, in the spirit of the
+/// "nodes" and "updates" reported by Knuth's DLX programs. Collected when
+/// searching with `SearchOptions::with_stats`, and reported by
+/// `DlxIterator::stats`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchStats {
+  nodes: u64,
+  updates: u64,
+  solutions: u64,
+  max_depth: usize,
+  branching_factors: Vec<BTreeMap<usize, u64>>,
+}
+
+impl SearchStats {
+  /// The number of nodes of the search tree visited, including solutions.
+  pub fn nodes(&self) -> u64 {
+    self.nodes
+  }
+
+  /// The number of links removed or restored while hiding and unhiding
+  /// subsets.
+  pub fn updates(&self) -> u64 {
+    self.updates
+  }
+
+  /// The number of solutions found.
+  pub fn solutions(&self) -> u64 {
+    self.solutions
+  }
+
+  /// The number of subsets chosen at the deepest node visited.
+  pub fn max_depth(&self) -> usize {
+    self.max_depth
+  }
+
+  /// For each depth of the search tree, a histogram mapping the branching
+  /// factor of the items chosen at that depth (the number of ways there were
+  /// to cover the item) to the number of times it was seen.
+  pub fn branching_factors(&self) -> &[BTreeMap<usize, u64>] {
+    &self.branching_factors
+  }
+
+  pub(super) fn record_node(&mut self, depth: usize) {
+    self.nodes += 1;
+    self.max_depth = self.max_depth.max(depth);
+  }
+
+  pub(super) fn record_branch(&mut self, depth: usize, branching_factor: usize) {
+    if self.branching_factors.len() <= depth {
+      self.branching_factors.resize_with(depth + 1, BTreeMap::new);
+    }
+    *self.branching_factors[depth]
+      .entry(branching_factor)
+      .or_default() += 1;
+  }
+
+  pub(super) fn record_solution(&mut self) {
+    self.solutions += 1;
+  }
+
+  pub(super) fn set_updates(&mut self, updates: u64) {
+    self.updates = updates;
+  }
+}
@@ -0,0 +1,448 @@
+use std::{
+  collections::HashMap,
+  error::Error,
+  fmt::{self, Debug, Display, Formatter},
+  iter,
+};
+
+use rand::Rng;
+
+use super::{
+  ChooseNextItemResult, Dlx, DlxExplorer, ExploreNextChoiceResult, HeaderType, Node, NodeType,
+};
+
+/// The ways `Dlx::build_zdd` can fail.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ZddError<I> {
+  /// A primary item has multiplicities, i.e. may or must be covered more
+  /// than once, which the diagram can't represent.
+  UnsupportedMultiplicity(I),
+}
+
+impl<I> Display for ZddError<I>
+where
+  I: Debug,
+{
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      ZddError::UnsupportedMultiplicity(item) => {
+        write!(
+          f,
+          "Item {item:?} has multiplicities, which aren't supported"
+        )
+      }
+    }
+  }
+}
+
+impl<I> Error for ZddError<I> where I: Debug {}
+
+/// A reference to a node of a `Zdd`. The first two are the terminals.
+type ZddRef = usize;
+
+/// The terminal reached by paths which aren't solutions.
+const BOTTOM: ZddRef = 0;
+/// The terminal reached by paths which are solutions.
+const TOP: ZddRef = 1;
+
+#[derive(Clone, Copy, Debug)]
+struct ZddNode {
+  /// The index of the subset this node decides on.
+  subset: usize,
+  /// The node to continue from if the subset isn't chosen.
+  lo: ZddRef,
+  /// The node to continue from if the subset is chosen.
+  hi: ZddRef,
+}
+
+/// A zero-suppressed decision diagram of all the solutions of a problem,
+/// from `Dlx::build_zdd`.
+///
+/// Every path from the root to the `TOP` terminal is one solution, made of
+/// the subsets whose nodes the path leaves through their `hi` edge. Shared
+/// sub-problems share nodes, so the diagram can be exponentially smaller than
+/// the list of solutions it represents.
+#[derive(Clone, Debug)]
+pub struct Zdd<N> {
+  names: Vec<N>,
+  /// The nodes of the diagram, where the first two are placeholders for the
+  /// terminals. Every node comes after the nodes it points to.
+  nodes: Vec<ZddNode>,
+  root: ZddRef,
+  /// The number of paths from each node to `TOP`, saturating at
+  /// `u128::MAX`.
+  counts: Vec<u128>,
+}
+
+impl<N> Zdd<N> {
+  fn new(names: Vec<N>, nodes: Vec<ZddNode>, root: ZddRef) -> Self {
+    let mut counts = vec![0u128; nodes.len()];
+    counts[TOP] = 1;
+    for (idx, node) in nodes.iter().enumerate().skip(2) {
+      counts[idx] = counts[node.lo].saturating_add(counts[node.hi]);
+    }
+    Self {
+      names,
+      nodes,
+      root,
+      counts,
+    }
+  }
+
+  /// The number of nodes in the diagram, not counting the terminals.
+  pub fn num_nodes(&self) -> usize {
+    self.nodes.len() - 2
+  }
+
+  /// The number of solutions, saturating at `u128::MAX`.
+  pub fn count(&self) -> u128 {
+    self.counts[self.root]
+  }
+
+  /// Iterates over all solutions, as the names of the subsets in each.
+  pub fn solutions(&self) -> ZddSolutions<'_, N> {
+    ZddSolutions {
+      zdd: self,
+      stack: vec![(self.root, 0)],
+      path: Vec::new(),
+    }
+  }
+
+  /// Draws a solution uniformly at random, or returns None if there are no
+  /// solutions. If there are more than `u128::MAX` solutions, the counts used
+  /// to weigh each choice saturate, and the sample is no longer uniform.
+  pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<Vec<&N>> {
+    if self.count() == 0 {
+      return None;
+    }
+    let mut solution = Vec::new();
+    let mut idx = self.root;
+    while idx != TOP {
+      let node = self.nodes[idx];
+      if rng.random_range(0..self.counts[idx]) < self.counts[node.hi] {
+        solution.push(&self.names[node.subset]);
+        idx = node.hi;
+      } else {
+        idx = node.lo;
+      }
+    }
+    Some(solution)
+  }
+}
+
+/// Iterates over the solutions in a `Zdd`, from `Zdd::solutions`.
+#[derive(Debug)]
+pub struct ZddSolutions<'a, N> {
+  zdd: &'a Zdd<N>,
+  /// The nodes left to visit, with the length of the path leading to each.
+  stack: Vec<(ZddRef, usize)>,
+  /// The subsets chosen on the way to the current node.
+  path: Vec<usize>,
+}
+
+impl<'a, N> Iterator for ZddSolutions<'a, N> {
+  type Item = Vec<&'a N>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    while let Some((idx, len)) = self.stack.pop() {
+      self.path.truncate(len);
+      match idx {
+        BOTTOM => {}
+        TOP => {
+          return Some(
+            self
+              .path
+              .iter()
+              .map(|&subset| &self.zdd.names[subset])
+              .collect(),
+          )
+        }
+        _ => {
+          let node = self.zdd.nodes[idx];
+          self.stack.push((node.lo, len));
+          self.path.push(node.subset);
+          self.stack.push((node.hi, len + 1));
+        }
+      }
+    }
+    None
+  }
+}
+
+/// The state of an item in a sub-problem, as stored in the keys of the memo.
+const ACTIVE: u64 = 0;
+const COVERED: u64 = 1;
+/// Secondary items which have been given a color are stored as the color
+/// plus this.
+const COLORED: u64 = 2;
+
+/// The fixed facts about a body node needed while building a `Zdd`, looked
+/// up once instead of walking to the node's header and boundary.
+#[derive(Clone, Copy)]
+struct NodeInfo {
+  /// The index of the item in the memo keys.
+  item: usize,
+  color: Option<u32>,
+  subset: usize,
+}
+
+struct ZddBuilder {
+  node_info: Vec<Option<NodeInfo>>,
+  /// The state of each item in the current sub-problem.
+  key: Vec<u64>,
+  memo: HashMap<Vec<u64>, ZddRef>,
+  nodes: Vec<ZddNode>,
+}
+
+impl ZddBuilder {
+  fn build<I, N>(&mut self, explorer: &mut DlxExplorer<&mut Dlx<I, N>, I, N>) -> ZddRef {
+    if let Some(&idx) = self.memo.get(&self.key) {
+      return idx;
+    }
+
+    let depth = explorer.partial_solution().len();
+    let idx = match explorer.choose_next_item() {
+      ChooseNextItemResult::FoundSolution => TOP,
+      ChooseNextItemResult::Continue => {
+        let mut children = Vec::new();
+        loop {
+          explorer.floor = depth;
+          let ExploreNextChoiceResult::Continue = explorer.explore_next_choice() else {
+            break;
+          };
+          let p = *explorer.partial_solution().last().unwrap();
+          let subset = self.node_info[p].unwrap().subset;
+
+          let previous = self.key.clone();
+          for q in iter::once(p).chain(explorer.dlx().iterate_items(p)) {
+            let info = self.node_info[q].unwrap();
+            self.key[info.item] = match info.color {
+              Some(color) => color as u64 + COLORED,
+              None => COVERED,
+            };
+          }
+          let child = self.build(explorer);
+          self.key = previous;
+
+          if child != BOTTOM {
+            children.push((subset, child));
+          }
+        }
+
+        // Chain the choices together, so leaving out one subset moves on to
+        // the next.
+        children.into_iter().rev().fold(BOTTOM, |lo, (subset, hi)| {
+          self.nodes.push(ZddNode { subset, lo, hi });
+          self.nodes.len() - 1
+        })
+      }
+    };
+
+    self.memo.insert(self.key.clone(), idx);
+    idx
+  }
+}
+
+impl<I, N> Dlx<I, N>
+where
+  I: Clone,
+  N: Clone,
+{
+  /// Builds a zero-suppressed decision diagram of all solutions, using
+  /// Knuth's Algorithm Z: the search remembers the diagram for every
+  /// sub-problem it solves, keyed by the state of the remaining items, and
+  /// reuses it whenever the same sub-problem comes up again. This makes it
+  /// feasible to count, enumerate lazily, and sample uniformly from problems
+  /// with far too many solutions to list.
+  ///
+  /// Only works for problems in which every primary item must be covered
+  /// exactly once, and fails with `ZddError::UnsupportedMultiplicity`
+  /// otherwise. The memo keeps one entry per distinct sub-problem, so memory
+  /// use grows with the number of distinct sub-problems rather than
+  /// solutions. The `Dlx` is left unmodified afterwards.
+  pub fn build_zdd(&mut self) -> Result<Zdd<N>, ZddError<I>> {
+    let num_items = self.headers.len() - 1;
+    for header in &self.headers[1..num_items] {
+      if let HeaderType::BoundedPrimary { min, max } = header.header_type {
+        if (min, max) != (1, 1) {
+          return Err(ZddError::UnsupportedMultiplicity(
+            header.item.clone().unwrap(),
+          ));
+        }
+      }
+    }
+
+    let mut names = Vec::new();
+    let mut node_info: Vec<Option<NodeInfo>> = vec![None; self.body.len()];
+    let mut start = self.headers.len();
+    for idx in self.headers.len()..self.body.len() {
+      match self.body_node(idx) {
+        Node::Boundary { name, .. } => {
+          for info in &mut node_info[start..idx] {
+            info.as_mut().unwrap().subset = names.len();
+          }
+          names.push(name.clone().unwrap());
+          start = idx + 1;
+        }
+        Node::Normal {
+          node_type: NodeType::Body { top, color, .. },
+          ..
+        } => {
+          node_info[idx] = Some(NodeInfo {
+            item: *top as usize,
+            color: *color,
+            subset: 0,
+          });
+        }
+        Node::Normal {
+          node_type: NodeType::Header { .. },
+          ..
+        } => dlx_unreachable!("Unexpected header in body"),
+      }
+    }
+
+    let mut builder = ZddBuilder {
+      node_info,
+      key: vec![ACTIVE; num_items],
+      memo: HashMap::new(),
+      nodes: vec![
+        ZddNode {
+          subset: 0,
+          lo: BOTTOM,
+          hi: BOTTOM,
+        };
+        2
+      ],
+    };
+    let root = builder.build(&mut DlxExplorer::new(self));
+    Ok(Zdd::new(names, builder.nodes, root))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use googletest::prelude::*;
+  use itertools::Itertools;
+  use rand::{rngs::StdRng, SeedableRng};
+
+  use crate::{
+    dlx::test::{random_problem, singletons_and_pairs},
+    Dlx, DlxIteratorWithNames, HeaderType, ZddError,
+  };
+
+  #[gtest]
+  fn test_zdd_counts_and_enumerates() {
+    let mut dlx = singletons_and_pairs();
+    let zdd = dlx.build_zdd().unwrap();
+    expect_eq!(zdd.count(), 764);
+    // Memoizing on the remaining items keeps the diagram far smaller than the
+    // search tree.
+    let search_nodes = dlx.find_solutions_stepwise().count();
+    expect_that!(zdd.num_nodes() * 5, lt(search_nodes));
+
+    let sorted = |solution: Vec<usize>| solution.into_iter().sorted().collect_vec();
+    expect_eq!(
+      zdd
+        .solutions()
+        .map(|solution| sorted(solution.into_iter().copied().collect()))
+        .sorted()
+        .collect_vec(),
+      dlx
+        .find_solutions()
+        .with_names()
+        .map(sorted)
+        .sorted()
+        .collect_vec()
+    );
+  }
+
+  #[gtest]
+  fn test_zdd_matches_search() {
+    for seed in 0..100 {
+      // Algorithm Z doesn't support multiplicities, so make every bounded
+      // item plain primary.
+      let (items, subsets) = random_problem(seed);
+      let items = items
+        .into_iter()
+        .map(|(item, header_type)| match header_type {
+          HeaderType::BoundedPrimary { .. } => (item, HeaderType::Primary),
+          _ => (item, header_type),
+        });
+      let mut dlx = Dlx::new(items, subsets);
+
+      let expected = dlx
+        .find_solutions()
+        .with_names()
+        .map(|solution| solution.into_iter().sorted().collect_vec())
+        .sorted()
+        .collect_vec();
+      let zdd = dlx.build_zdd().unwrap();
+      expect_eq!(zdd.count(), expected.len() as u128, "seed {seed}");
+      expect_eq!(
+        zdd
+          .solutions()
+          .map(|solution| solution.into_iter().copied().sorted().collect_vec())
+          .sorted()
+          .collect_vec(),
+        expected,
+        "seed {seed}"
+      );
+    }
+  }
+
+  #[gtest]
+  fn test_zdd_sample_is_uniform() {
+    // Choosing the singleton for the first item leaves 10 solutions, while
+    // each pair leaves 4.
+    let mut dlx = Dlx::new(
+      (0..5).map(|item| (item, HeaderType::Primary)),
+      (0..5)
+        .map(|item| vec![item])
+        .chain((0..5).tuple_combinations().map(|(a, b)| vec![a, b]))
+        .enumerate(),
+    );
+    let zdd = dlx.build_zdd().unwrap();
+    expect_eq!(zdd.count(), 26);
+
+    let mut rng = StdRng::seed_from_u64(1);
+    let counts = (0..26 * 200)
+      .map(|_| {
+        zdd
+          .sample(&mut rng)
+          .unwrap()
+          .into_iter()
+          .sorted()
+          .collect_vec()
+      })
+      .counts();
+    expect_eq!(counts.len(), 26);
+    for (solution, count) in counts {
+      expect_that!(count, ge(140).and(le(260)), "{solution:?}");
+    }
+  }
+
+  #[gtest]
+  fn test_zdd_no_solutions() {
+    let mut dlx = Dlx::new(
+      vec![('p', HeaderType::Primary), ('q', HeaderType::Primary)],
+      vec![(0, vec!['p']), (1, vec!['p'])],
+    );
+    let zdd = dlx.build_zdd().unwrap();
+    expect_eq!(zdd.count(), 0);
+    expect_eq!(zdd.num_nodes(), 0);
+    expect_that!(zdd.solutions().next(), none());
+    expect_that!(zdd.sample(&mut StdRng::seed_from_u64(1)), none());
+  }
+
+  #[gtest]
+  fn test_zdd_rejects_multiplicities() {
+    let mut dlx = Dlx::new(
+      vec![('p', HeaderType::BoundedPrimary { min: 1, max: 2 })],
+      vec![(0, vec!['p']), (1, vec!['p'])],
+    );
+    expect_that!(
+      dlx.build_zdd().err(),
+      some(eq(&ZddError::UnsupportedMultiplicity('p')))
+    );
+  }
+}
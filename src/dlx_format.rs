@@ -0,0 +1,459 @@
+use std::{
+  collections::{HashMap, HashSet},
+  error::Error,
+  fmt::{self, Display, Formatter},
+  io,
+};
+
+use crate::{ColorItem, Constraint, Dlx, HeaderType};
+
+/// The ways a problem in Knuth's DLX format can be malformed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DlxParseErrorKind {
+  /// The input has no line listing the items.
+  MissingItems,
+  /// The items line has more than one `|` separator.
+  ExtraSeparator,
+  /// An item name is empty or contains `:` or `|`.
+  InvalidItemName(String),
+  /// The multiplicity prefix of a primary item isn't of the form `u:v|` or
+  /// `v|` with `u <= v` and `v > 0`.
+  InvalidBounds(String),
+  /// The same item is listed more than once on the items line.
+  DuplicateItem(String),
+  /// An option references an item which isn't on the items line.
+  UnknownItem(String),
+  /// An option lists the same item more than once.
+  DuplicateItemInOption(String),
+  /// An option gives a color to a primary item.
+  ColoredPrimaryItem(String),
+  /// An option has no primary items, so it could never be chosen.
+  NoPrimaryItems,
+}
+
+impl Display for DlxParseErrorKind {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      DlxParseErrorKind::MissingItems => write!(f, "Missing items line"),
+      DlxParseErrorKind::ExtraSeparator => write!(f, "More than one '|' in items line"),
+      DlxParseErrorKind::InvalidItemName(name) => write!(f, "Invalid item name {name:?}"),
+      DlxParseErrorKind::InvalidBounds(item) => write!(f, "Invalid bounds for item {item:?}"),
+      DlxParseErrorKind::DuplicateItem(name) => write!(f, "Duplicate item {name:?}"),
+      DlxParseErrorKind::UnknownItem(name) => write!(f, "Unknown item {name:?}"),
+      DlxParseErrorKind::DuplicateItemInOption(name) => {
+        write!(f, "Item {name:?} appears more than once in option")
+      }
+      DlxParseErrorKind::ColoredPrimaryItem(name) => {
+        write!(f, "Primary item {name:?} can't have a color")
+      }
+      DlxParseErrorKind::NoPrimaryItems => write!(f, "Option has no primary items"),
+    }
+  }
+}
+
+/// An error encountered while parsing a problem in Knuth's DLX format, along
+/// with the (1-indexed) line it was found on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DlxParseError {
+  line: usize,
+  kind: DlxParseErrorKind,
+}
+
+impl DlxParseError {
+  fn new(line: usize, kind: DlxParseErrorKind) -> Self {
+    Self { line, kind }
+  }
+
+  pub fn line(&self) -> usize {
+    self.line
+  }
+
+  pub fn kind(&self) -> &DlxParseErrorKind {
+    &self.kind
+  }
+}
+
+impl Display for DlxParseError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "line {}: {}", self.line, self.kind)
+  }
+}
+
+impl Error for DlxParseError {}
+
+fn is_valid_item_name(name: &str) -> bool {
+  !name.is_empty() && !name.contains([':', '|']) && !name.contains(char::is_whitespace)
+}
+
+/// Parses the multiplicity prefix of a primary item, either `u:v` or `v`
+/// (meaning `v:v`).
+fn parse_bounds(bounds: &str) -> Option<(u32, u32)> {
+  let (min, max) = match bounds.split_once(':') {
+    Some((min, max)) => (min.parse().ok()?, max.parse().ok()?),
+    None => {
+      let max = bounds.parse().ok()?;
+      (max, max)
+    }
+  };
+  (min <= max && max != 0).then_some((min, max))
+}
+
+fn parse_items(line_no: usize, line: &str) -> Result<Vec<(String, HeaderType)>, DlxParseError> {
+  let error = |kind| Err(DlxParseError::new(line_no, kind));
+
+  let mut items: Vec<(String, HeaderType)> = Vec::new();
+  let mut secondary = false;
+  for token in line.split_whitespace() {
+    if token == "|" {
+      if secondary {
+        return error(DlxParseErrorKind::ExtraSeparator);
+      }
+      secondary = true;
+      continue;
+    }
+
+    let (name, header_type) = match token.split_once('|') {
+      Some((bounds, name)) if !secondary => match parse_bounds(bounds) {
+        Some((min, max)) => (name, HeaderType::BoundedPrimary { min, max }),
+        None => return error(DlxParseErrorKind::InvalidBounds(token.to_owned())),
+      },
+      _ if secondary => (token, HeaderType::Secondary),
+      _ => (token, HeaderType::Primary),
+    };
+    if !is_valid_item_name(name) {
+      return error(DlxParseErrorKind::InvalidItemName(token.to_owned()));
+    }
+    if items.iter().any(|(item, _)| item == name) {
+      return error(DlxParseErrorKind::DuplicateItem(name.to_owned()));
+    }
+    items.push((name.to_owned(), header_type));
+  }
+
+  Ok(items)
+}
+
+/// Parses a problem in the plain-text format used by Knuth's `dlx1`/`dlx2`
+/// programs.
+///
+/// The first line lists the primary items, then a `|`, then the secondary
+/// items (the `|` may be omitted if there are no secondary items). Primary
+/// items may be prefixed with `u:v|` to be covered between `u` and `v` times,
+/// or with `v|` to be covered exactly `v` times. Each following line is an
+/// option listing its items, where secondary items may be given a color with
+/// `item:color`. Blank lines and lines starting with `|` are ignored.
+///
+/// Subsets are named by the index of their option, starting from 0. Colors
+/// are numbered in order of first appearance, and uncolored uses of secondary
+/// items become `Constraint::SecondaryUncolored`, so they conflict with every
+/// other use of that item.
+pub fn parse_dlx(input: &str) -> Result<Dlx<String, usize>, DlxParseError> {
+  let mut lines = input
+    .lines()
+    .enumerate()
+    .map(|(idx, line)| (idx + 1, line.trim()))
+    .filter(|(_, line)| !line.is_empty() && !line.starts_with('|'));
+
+  let Some((line_no, line)) = lines.next() else {
+    return Err(DlxParseError::new(1, DlxParseErrorKind::MissingItems));
+  };
+  let items = parse_items(line_no, line)?;
+  let header_types: HashMap<_, _> = items
+    .iter()
+    .map(|(item, header_type)| (item.as_str(), *header_type))
+    .collect();
+
+  let mut colors = HashMap::new();
+  let mut next_color = 0;
+  let mut subsets = Vec::new();
+  for (line_no, line) in lines {
+    let error = |kind| Err(DlxParseError::new(line_no, kind));
+
+    let mut seen = HashSet::new();
+    let mut constraints = Vec::new();
+    for token in line.split_whitespace() {
+      let (name, color) = match token.split_once(':') {
+        Some((name, color)) => (name, Some(color)),
+        None => (token, None),
+      };
+      let Some(header_type) = header_types.get(name) else {
+        return error(DlxParseErrorKind::UnknownItem(name.to_owned()));
+      };
+      if !seen.insert(name) {
+        return error(DlxParseErrorKind::DuplicateItemInOption(name.to_owned()));
+      }
+
+      constraints.push(match (header_type, color) {
+        (HeaderType::Secondary, Some(color)) => {
+          let color = *colors.entry(color).or_insert_with(|| {
+            next_color += 1;
+            next_color - 1
+          });
+          ColorItem::new(name.to_owned(), color).into()
+        }
+        (HeaderType::Secondary, None) => Constraint::SecondaryUncolored(name.to_owned()),
+        (_, Some(_)) => return error(DlxParseErrorKind::ColoredPrimaryItem(name.to_owned())),
+        (_, None) => Constraint::Primary(name.to_owned()),
+      });
+    }
+
+    if !constraints
+      .iter()
+      .any(|constraint| matches!(constraint, Constraint::Primary(_)))
+    {
+      return error(DlxParseErrorKind::NoPrimaryItems);
+    }
+    subsets.push((subsets.len(), constraints));
+  }
+
+  Ok(Dlx::try_new(items, subsets).expect("Problem was validated while parsing"))
+}
+
+/// Writes `dlx` in the format read by `parse_dlx`, with one option per
+/// subset in the order they are tried. Subset names are not written, and
+/// colors are written as numbers.
+///
+/// Returns an error of kind `InvalidInput`, without writing anything, if the
+/// problem couldn't be read back the same way: if any item name is empty or
+/// contains whitespace, `:` or `|`, or if a subset has no primary items,
+/// which includes empty subsets, since `parse_dlx` rejects the former and
+/// skips the blank lines the latter would be written as.
+pub fn write_dlx<I, N, W>(dlx: &Dlx<I, N>, mut out: W) -> io::Result<()>
+where
+  I: Clone + Display,
+  W: io::Write,
+{
+  let invalid_input = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+  let item_name = |item: &I| {
+    let name = item.to_string();
+    if is_valid_item_name(&name) {
+      Ok(name)
+    } else {
+      Err(invalid_input(format!(
+        "Item name {name:?} can't be written in DLX format"
+      )))
+    }
+  };
+
+  // Check every subset before writing anything.
+  for (idx, (_, constraints)) in dlx.subsets().enumerate() {
+    if !constraints
+      .iter()
+      .any(|constraint| matches!(constraint, Constraint::Primary(_)))
+    {
+      return Err(invalid_input(format!(
+        "Subset {idx} has no primary items, so can't be written in DLX format"
+      )));
+    }
+  }
+
+  let mut lines = Vec::new();
+  let mut item_tokens = Vec::new();
+  let mut wrote_separator = false;
+  for (item, header_type) in dlx.items() {
+    let name = item_name(item)?;
+    if matches!(header_type, HeaderType::Secondary) && !wrote_separator {
+      item_tokens.push("|".to_owned());
+      wrote_separator = true;
+    }
+    item_tokens.push(match header_type {
+      HeaderType::BoundedPrimary { min, max } => format!("{min}:{max}|{name}"),
+      HeaderType::Primary | HeaderType::Secondary => name,
+    });
+  }
+  lines.push(item_tokens.join(" "));
+
+  for (_, constraints) in dlx.subsets() {
+    let tokens = constraints
+      .iter()
+      .map(|constraint| {
+        Ok(match constraint {
+          Constraint::Primary(item) => item_name(item)?,
+          Constraint::Secondary(color_item) => {
+            format!("{}:{}", item_name(color_item.item())?, color_item.color())
+          }
+          Constraint::SecondaryUncolored(item) => item_name(item)?,
+        })
+      })
+      .collect::<io::Result<Vec<_>>>()?;
+    lines.push(tokens.join(" "));
+  }
+
+  for line in lines {
+    writeln!(out, "{line}")?;
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use googletest::prelude::*;
+  use itertools::Itertools;
+
+  use std::{fmt::Display, io};
+
+  use crate::{
+    parse_dlx, write_dlx, Constraint, Dlx, DlxIteratorWithNames, DlxParseErrorKind, HeaderType,
+  };
+
+  fn sorted_solutions(dlx: &mut Dlx<String, usize>) -> Vec<Vec<usize>> {
+    dlx
+      .find_solutions()
+      .with_names()
+      .map(|solution| solution.into_iter().sorted().collect_vec())
+      .sorted()
+      .collect()
+  }
+
+  #[gtest]
+  fn test_parse_exact_cover() {
+    let mut dlx = parse_dlx(
+      "| An exact cover problem.
+       a b c d e f g
+       c e
+       a d g
+       b c f
+       a d f
+       b g
+       d e g
+      ",
+    )
+    .unwrap();
+
+    expect_that!(
+      sorted_solutions(&mut dlx),
+      elements_are![elements_are![&0, &3, &4]]
+    );
+  }
+
+  #[gtest]
+  fn test_parse_colors() {
+    let mut dlx = parse_dlx(
+      "p q r | x y
+       p q x y:A
+       p r x:A y
+       p x:B
+       q x:A
+       r y:B
+      ",
+    )
+    .unwrap();
+
+    expect_that!(
+      sorted_solutions(&mut dlx),
+      elements_are![elements_are![&1, &3]]
+    );
+  }
+
+  #[gtest]
+  fn test_parse_multiplicities() {
+    let dlx = parse_dlx("2:3|a 2|b c\na b\na c\n").unwrap();
+
+    expect_that!(
+      dlx.items().collect_vec(),
+      elements_are![
+        (eq(&"a"), eq(&HeaderType::BoundedPrimary { min: 2, max: 3 })),
+        (eq(&"b"), eq(&HeaderType::BoundedPrimary { min: 2, max: 2 })),
+        (eq(&"c"), eq(&HeaderType::Primary)),
+      ]
+    );
+  }
+
+  #[gtest]
+  fn test_parse_errors() {
+    let error_at = |input: &str| {
+      let err = parse_dlx(input).unwrap_err();
+      (err.line(), err.kind().clone())
+    };
+
+    expect_eq!(
+      error_at("| Nothing\n"),
+      (1, DlxParseErrorKind::MissingItems)
+    );
+    expect_eq!(
+      error_at("a | b | c\n"),
+      (1, DlxParseErrorKind::ExtraSeparator)
+    );
+    expect_eq!(
+      error_at("a b a\n"),
+      (1, DlxParseErrorKind::DuplicateItem("a".to_owned()))
+    );
+    expect_eq!(
+      error_at("3:2|a\n"),
+      (1, DlxParseErrorKind::InvalidBounds("3:2|a".to_owned()))
+    );
+    expect_eq!(
+      error_at("a | b:c\n"),
+      (1, DlxParseErrorKind::InvalidItemName("b:c".to_owned()))
+    );
+    expect_eq!(
+      error_at("a b | x\n\n| Comment\na b\na c\n"),
+      (5, DlxParseErrorKind::UnknownItem("c".to_owned()))
+    );
+    expect_eq!(
+      error_at("a b | x\na x:1 b x\n"),
+      (2, DlxParseErrorKind::DuplicateItemInOption("x".to_owned()))
+    );
+    expect_eq!(
+      error_at("a b | x\na:1\n"),
+      (2, DlxParseErrorKind::ColoredPrimaryItem("a".to_owned()))
+    );
+    expect_eq!(
+      error_at("a b | x\na b\nx:1\n"),
+      (3, DlxParseErrorKind::NoPrimaryItems)
+    );
+  }
+
+  #[gtest]
+  fn test_round_trip() {
+    let input = "1:2|p q r | x y\np q x:0 y\np r x:1 y:2\np x:3\nq x:1\nr y:3\nr\n";
+    let mut dlx = parse_dlx(input).unwrap();
+
+    let mut output = Vec::new();
+    write_dlx(&dlx, &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    expect_eq!(output, input);
+
+    let mut reparsed = parse_dlx(&output).unwrap();
+    expect_eq!(sorted_solutions(&mut reparsed), sorted_solutions(&mut dlx));
+  }
+
+  /// Writes `dlx`, expecting it to be rejected before anything is written.
+  fn expect_write_rejected<I, N>(dlx: &Dlx<I, N>)
+  where
+    I: Clone + Display,
+  {
+    let mut output = Vec::new();
+    expect_that!(
+      write_dlx(dlx, &mut output).map_err(|err| err.kind()),
+      err(eq(io::ErrorKind::InvalidInput))
+    );
+    expect_that!(output, empty());
+  }
+
+  #[gtest]
+  fn test_write_invalid_item_name() {
+    let dlx = Dlx::new(vec![("a b", HeaderType::Primary)], vec![(0, vec!["a b"])]);
+    expect_write_rejected(&dlx);
+  }
+
+  #[gtest]
+  fn test_write_subset_without_primary_items() {
+    let dlx = Dlx::new(
+      vec![("b", HeaderType::Primary), ("s", HeaderType::Secondary)],
+      vec![
+        (0, vec![Constraint::SecondaryUncolored("s")]),
+        (1, vec![Constraint::Primary("b")]),
+      ],
+    );
+    expect_write_rejected(&dlx);
+  }
+
+  #[gtest]
+  fn test_write_empty_subset() {
+    let dlx = Dlx::new(
+      vec![("b", HeaderType::Primary)],
+      vec![(0, vec![]), (1, vec!["b"])],
+    );
+    expect_write_rejected(&dlx);
+  }
+}
@@ -1,3 +1,11 @@
 mod dlx;
+mod dlx_format;
+mod nqueens;
+mod polyomino;
+mod sudoku;
 
 pub use dlx::*;
+pub use dlx_format::*;
+pub use nqueens::*;
+pub use polyomino::*;
+pub use sudoku::*;
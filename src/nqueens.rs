@@ -0,0 +1,139 @@
+use crate::{Constraint, Dlx, HeaderType};
+
+/// An item of the exact cover problem built by `nqueens`. Rows and columns
+/// are numbered from 0, and the diagonals of each direction are numbered
+/// from the top right corner for `Diagonal` and the top left for
+/// `AntiDiagonal`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum QueensItem {
+  /// Row `row` has a queen.
+  Row { row: usize },
+  /// Column `col` has a queen.
+  Column { col: usize },
+  /// A secondary item for the diagonal running down and to the right through
+  /// the cells with `row - col + n - 1 == index`.
+  Diagonal { index: usize },
+  /// A secondary item for the diagonal running down and to the left through
+  /// the cells with `row + col == index`.
+  AntiDiagonal { index: usize },
+}
+
+/// The subsets of the exact cover problem built by `nqueens`: placing a queen
+/// in the cell at `(row, col)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Queen {
+  row: usize,
+  col: usize,
+}
+
+impl Queen {
+  pub fn new(row: usize, col: usize) -> Self {
+    Self { row, col }
+  }
+
+  pub fn row(&self) -> usize {
+    self.row
+  }
+
+  pub fn col(&self) -> usize {
+    self.col
+  }
+}
+
+/// Builds the exact cover problem of placing `n` queens on an `n`x`n` board
+/// so that none attack each other. Every row and column is a primary item,
+/// as it must have exactly one queen, while the `2n - 1` diagonals in each
+/// direction are secondary items, as they may have at most one.
+pub fn nqueens(n: usize) -> Dlx<QueensItem, Queen> {
+  let diagonals = (2 * n).saturating_sub(1);
+  let items = (0..n)
+    .flat_map(|i| [QueensItem::Row { row: i }, QueensItem::Column { col: i }])
+    .map(|item| (item, HeaderType::Primary))
+    .chain(
+      (0..diagonals)
+        .flat_map(|index| {
+          [
+            QueensItem::Diagonal { index },
+            QueensItem::AntiDiagonal { index },
+          ]
+        })
+        .map(|item| (item, HeaderType::Secondary)),
+    );
+  let subsets = (0..n)
+    .flat_map(|row| (0..n).map(move |col| Queen::new(row, col)))
+    .map(|queen| {
+      let Queen { row, col } = queen;
+      (
+        queen,
+        [
+          Constraint::Primary(QueensItem::Row { row }),
+          Constraint::Primary(QueensItem::Column { col }),
+          Constraint::SecondaryUncolored(QueensItem::Diagonal {
+            index: row + n - 1 - col,
+          }),
+          Constraint::SecondaryUncolored(QueensItem::AntiDiagonal { index: row + col }),
+        ],
+      )
+    });
+  Dlx::new(items, subsets)
+}
+
+/// Counts the solutions of the `n` queens problem: there are 92 for `n = 8`,
+/// and 14,200 for `n = 12`. This is the workload timed by `cargo bench`, for
+/// comparing the speed of the solver across changes.
+pub fn count_nqueens(n: usize) -> u64 {
+  nqueens(n).count_solutions()
+}
+
+/// Draws the `n`x`n` board with `queens` on it, with one line per row, `Q`
+/// for a queen and `.` for an empty cell.
+pub fn render_queens(n: usize, queens: &[Queen]) -> String {
+  let mut board = vec![vec!['.'; n]; n];
+  for queen in queens {
+    board[queen.row][queen.col] = 'Q';
+  }
+  board
+    .into_iter()
+    .map(|row| row.into_iter().chain(['\n']).collect::<String>())
+    .collect()
+}
+
+#[cfg(test)]
+mod test {
+  use googletest::prelude::*;
+  use itertools::Itertools;
+
+  use crate::{count_nqueens, nqueens, render_queens, DlxIteratorWithNames, HeaderType, Queen};
+
+  #[gtest]
+  fn test_count_nqueens() {
+    let counts = (1..=9).map(count_nqueens).collect_vec();
+    expect_eq!(counts, vec![1, 0, 0, 2, 10, 4, 40, 92, 352]);
+  }
+
+  #[gtest]
+  fn test_nqueens_items() {
+    let dlx = nqueens(8);
+    let counts = dlx.items().map(|(_, header_type)| header_type).counts();
+    expect_eq!(counts[&HeaderType::Primary], 16);
+    expect_eq!(counts[&HeaderType::Secondary], 30);
+  }
+
+  #[gtest]
+  fn test_render_queens() {
+    let solutions = nqueens(4)
+      .find_solutions()
+      .with_names()
+      .map(|queens| render_queens(4, &queens))
+      .sorted()
+      .collect_vec();
+    expect_eq!(
+      solutions,
+      vec![
+        "..Q.\nQ...\n...Q\n.Q..\n".to_owned(),
+        ".Q..\n...Q\nQ...\n..Q.\n".to_owned(),
+      ]
+    );
+    expect_eq!(render_queens(2, &[Queen::new(1, 0)]), "..\nQ.\n");
+  }
+}
@@ -0,0 +1,448 @@
+use std::collections::BTreeSet;
+
+use crate::{Dlx, HeaderType};
+
+/// An item of the exact cover problem built by `PolyominoTiling::dlx`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PolyominoItem {
+  /// Piece `index`, in the order the pieces were added, is placed.
+  Piece { index: usize },
+  /// The board cell at `(row, col)` is covered.
+  Cell { row: usize, col: usize },
+}
+
+/// The ways a piece may be turned before it is placed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Orientations {
+  /// The piece is only moved, never turned.
+  Fixed,
+  /// The piece may be rotated by multiples of 90 degrees.
+  Rotate,
+  /// The piece may be rotated, and flipped over.
+  RotateAndReflect,
+}
+
+/// A piece made of cells, as `(row, col)` positions, with a label to draw
+/// it with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Polyomino {
+  label: char,
+  cells: BTreeSet<(usize, usize)>,
+  orientations: Orientations,
+}
+
+impl Polyomino {
+  /// Creates a piece from its cells, which may be rotated and reflected.
+  /// Panics if there are no cells.
+  pub fn new(label: char, cells: impl IntoIterator<Item = (usize, usize)>) -> Self {
+    let cells = cells.into_iter().collect::<BTreeSet<_>>();
+    assert!(!cells.is_empty(), "Polyomino {label} has no cells");
+    Self {
+      label,
+      cells,
+      orientations: Orientations::RotateAndReflect,
+    }
+  }
+
+  /// Creates a piece from a drawing of its shape, with rows separated by
+  /// whitespace, where `.` is an empty cell and any other character is part
+  /// of the piece, e.g. `".FF FF. .F."` for the F pentomino.
+  pub fn from_shape(label: char, shape: &str) -> Self {
+    Self::new(label, cells_of_drawing(shape))
+  }
+
+  /// The 12 pentominoes, labelled by their usual letters.
+  pub fn pentominoes() -> Vec<Self> {
+    [
+      ('F', ".FF FF. .F."),
+      ('I', "IIIII"),
+      ('L', "LLLL L..."),
+      ('N', "NN.. .NNN"),
+      ('P', "PP PP P."),
+      ('T', "TTT .T. .T."),
+      ('U', "U.U UUU"),
+      ('V', "V.. V.. VVV"),
+      ('W', "W.. WW. .WW"),
+      ('X', ".X. XXX .X."),
+      ('Y', "YYYY .Y.."),
+      ('Z', "ZZ. .Z. .ZZ"),
+    ]
+    .into_iter()
+    .map(|(label, shape)| Self::from_shape(label, shape))
+    .collect()
+  }
+
+  /// Restricts the ways this piece may be turned. Fixing a piece which the
+  /// board's symmetries could turn removes the rotated or reflected copies of
+  /// each solution.
+  pub fn with_orientations(mut self, orientations: Orientations) -> Self {
+    self.orientations = orientations;
+    self
+  }
+
+  pub fn label(&self) -> char {
+    self.label
+  }
+
+  pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+    self.cells.iter().copied()
+  }
+
+  pub fn orientations(&self) -> Orientations {
+    self.orientations
+  }
+
+  /// The distinct shapes this piece can be turned into, each moved so its
+  /// topmost row and leftmost column are 0.
+  fn oriented_shapes(&self) -> BTreeSet<Vec<(usize, usize)>> {
+    let (rotations, reflections) = match self.orientations {
+      Orientations::Fixed => (1, 1),
+      Orientations::Rotate => (4, 1),
+      Orientations::RotateAndReflect => (4, 2),
+    };
+    (0..reflections)
+      .flat_map(|reflection| (0..rotations).map(move |rotation| (reflection, rotation)))
+      .map(|(reflection, rotation)| {
+        let cells = self
+          .cells()
+          .map(|(row, col)| {
+            let (mut row, mut col) = (row as isize, col as isize);
+            if reflection == 1 {
+              col = -col;
+            }
+            for _ in 0..rotation {
+              (row, col) = (col, -row);
+            }
+            (row, col)
+          })
+          .collect::<Vec<_>>();
+        let top = cells.iter().map(|&(row, _)| row).min().unwrap();
+        let left = cells.iter().map(|&(_, col)| col).min().unwrap();
+        let mut shape = cells
+          .into_iter()
+          .map(|(row, col)| ((row - top) as usize, (col - left) as usize))
+          .collect::<Vec<_>>();
+        shape.sort_unstable();
+        shape
+      })
+      .collect()
+  }
+}
+
+/// The region to tile, as a set of cells within a `rows`x`cols` rectangle.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PolyominoBoard {
+  rows: usize,
+  cols: usize,
+  cells: BTreeSet<(usize, usize)>,
+}
+
+impl PolyominoBoard {
+  /// A board covering the whole `rows`x`cols` rectangle.
+  pub fn rectangle(rows: usize, cols: usize) -> Self {
+    Self {
+      rows,
+      cols,
+      cells: (0..rows)
+        .flat_map(|row| (0..cols).map(move |col| (row, col)))
+        .collect(),
+    }
+  }
+
+  /// Creates a board from a mask in the format of `Polyomino::from_shape`,
+  /// where `.` is a hole and any other character is a cell to cover, e.g.
+  /// `"#### #..# ####"` for a ring.
+  pub fn from_mask(mask: &str) -> Self {
+    let cells = cells_of_drawing(mask);
+    Self {
+      rows: cells.iter().map(|&(row, _)| row + 1).max().unwrap_or(0),
+      cols: cells.iter().map(|&(_, col)| col + 1).max().unwrap_or(0),
+      cells,
+    }
+  }
+
+  pub fn rows(&self) -> usize {
+    self.rows
+  }
+
+  pub fn cols(&self) -> usize {
+    self.cols
+  }
+
+  pub fn contains(&self, row: usize, col: usize) -> bool {
+    self.cells.contains(&(row, col))
+  }
+
+  pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+    self.cells.iter().copied()
+  }
+}
+
+/// The cells marked in a drawing with rows separated by whitespace, where `.`
+/// is an unmarked cell.
+fn cells_of_drawing(drawing: &str) -> BTreeSet<(usize, usize)> {
+  drawing
+    .split_whitespace()
+    .enumerate()
+    .flat_map(|(row, line)| {
+      line
+        .chars()
+        .enumerate()
+        .filter(|&(_, c)| c != '.')
+        .map(move |(col, _)| (row, col))
+    })
+    .collect()
+}
+
+/// The subsets of the exact cover problem built by `PolyominoTiling::dlx`:
+/// placing piece `piece` on `cells` of the board.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Placement {
+  piece: usize,
+  cells: Vec<(usize, usize)>,
+}
+
+impl Placement {
+  pub fn piece(&self) -> usize {
+    self.piece
+  }
+
+  pub fn cells(&self) -> &[(usize, usize)] {
+    &self.cells
+  }
+}
+
+/// Tiling a board with a set of pieces, solved as an exact cover problem:
+/// every piece is placed once, and every cell of the board is covered once.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PolyominoTiling {
+  board: PolyominoBoard,
+  pieces: Vec<Polyomino>,
+}
+
+impl PolyominoTiling {
+  pub fn new(board: PolyominoBoard) -> Self {
+    Self {
+      board,
+      pieces: Vec::new(),
+    }
+  }
+
+  pub fn with_piece(mut self, piece: Polyomino) -> Self {
+    self.pieces.push(piece);
+    self
+  }
+
+  pub fn with_pieces(mut self, pieces: impl IntoIterator<Item = Polyomino>) -> Self {
+    self.pieces.extend(pieces);
+    self
+  }
+
+  pub fn board(&self) -> &PolyominoBoard {
+    &self.board
+  }
+
+  pub fn pieces(&self) -> &[Polyomino] {
+    &self.pieces
+  }
+
+  /// Every way to place each piece on the board, in each of its allowed
+  /// orientations.
+  pub fn placements(&self) -> Vec<Placement> {
+    let mut placements = Vec::new();
+    for (piece, polyomino) in self.pieces.iter().enumerate() {
+      for shape in polyomino.oriented_shapes() {
+        for (top, left) in
+          (0..self.board.rows).flat_map(|row| (0..self.board.cols).map(move |col| (row, col)))
+        {
+          let cells = shape
+            .iter()
+            .map(|&(row, col)| (top + row, left + col))
+            .collect::<Vec<_>>();
+          if cells
+            .iter()
+            .all(|&(row, col)| self.board.contains(row, col))
+          {
+            placements.push(Placement { piece, cells });
+          }
+        }
+      }
+    }
+    placements
+  }
+
+  /// Builds the exact cover problem, with a primary item for each piece and
+  /// each cell of the board, and a subset for each placement.
+  pub fn dlx(&self) -> Dlx<PolyominoItem, Placement> {
+    let items = (0..self.pieces.len())
+      .map(|index| PolyominoItem::Piece { index })
+      .chain(
+        self
+          .board
+          .cells()
+          .map(|(row, col)| PolyominoItem::Cell { row, col }),
+      )
+      .map(|item| (item, HeaderType::Primary));
+    let subsets = self.placements().into_iter().map(|placement| {
+      let items = std::iter::once(PolyominoItem::Piece {
+        index: placement.piece,
+      })
+      .chain(
+        placement
+          .cells
+          .iter()
+          .map(|&(row, col)| PolyominoItem::Cell { row, col }),
+      )
+      .collect::<Vec<_>>();
+      (placement, items)
+    });
+    Dlx::new(items, subsets)
+  }
+
+  /// Draws the board with one line per row, labelling each cell with the
+  /// piece placed on it, and `.` for holes and cells left uncovered.
+  pub fn render(&self, placements: &[Placement]) -> String {
+    let mut board = vec![vec!['.'; self.board.cols]; self.board.rows];
+    for placement in placements {
+      for &(row, col) in &placement.cells {
+        board[row][col] = self.pieces[placement.piece].label;
+      }
+    }
+    board
+      .into_iter()
+      .map(|row| row.into_iter().chain(['\n']).collect::<String>())
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::collections::BTreeSet;
+
+  use googletest::prelude::*;
+  use itertools::Itertools;
+
+  use crate::{
+    DlxIteratorWithNames, Orientations, Placement, Polyomino, PolyominoBoard, PolyominoTiling,
+  };
+
+  #[gtest]
+  fn test_placements() {
+    let board = PolyominoBoard::rectangle(2, 3);
+    let domino = Polyomino::from_shape('D', "DD");
+    let tiling = PolyominoTiling::new(board.clone()).with_piece(domino.clone());
+    expect_eq!(tiling.placements().len(), 7);
+    let tiling = PolyominoTiling::new(board.clone())
+      .with_piece(domino.clone().with_orientations(Orientations::Rotate));
+    expect_eq!(tiling.placements().len(), 7);
+    let tiling =
+      PolyominoTiling::new(board).with_piece(domino.with_orientations(Orientations::Fixed));
+    expect_eq!(tiling.placements().len(), 4);
+
+    // The L tetromino has 4 rotations, and 4 more reflections.
+    let l = Polyomino::from_shape('L', "LLL L..");
+    expect_eq!(l.oriented_shapes().len(), 8);
+    expect_eq!(
+      l.with_orientations(Orientations::Rotate)
+        .oriented_shapes()
+        .len(),
+      4
+    );
+    expect_eq!(
+      Polyomino::from_shape('X', ".X. XXX .X.")
+        .oriented_shapes()
+        .len(),
+      1
+    );
+  }
+
+  #[gtest]
+  fn test_board_mask() {
+    let board = PolyominoBoard::from_mask(".## ###");
+    expect_eq!((board.rows(), board.cols()), (2, 3));
+    expect_false!(board.contains(0, 0));
+    expect_true!(board.contains(1, 0));
+
+    let tiling = PolyominoTiling::new(board.clone())
+      .with_piece(Polyomino::from_shape('A', "AA"))
+      .with_piece(Polyomino::from_shape('B', "BBB"));
+    let solutions = tiling.dlx().find_solutions().with_names().collect_vec();
+    expect_eq!(solutions.len(), 1);
+    expect_eq!(tiling.render(&solutions[0]), ".AA\nBBB\n");
+    expect_eq!(tiling.render(&[]), "...\n...\n");
+
+    // Keeping the domino upright leaves no room for the tromino.
+    let tiling = PolyominoTiling::new(board)
+      .with_piece(Polyomino::from_shape('A', "A A").with_orientations(Orientations::Fixed))
+      .with_piece(Polyomino::from_shape('B', "BBB"));
+    expect_eq!(tiling.dlx().count_solutions(), 0);
+  }
+
+  /// The shape of a placement, moved to the top left corner.
+  fn placement_shape(placement: &Placement) -> Vec<(usize, usize)> {
+    let top = placement.cells().iter().map(|&(row, _)| row).min().unwrap();
+    let left = placement.cells().iter().map(|&(_, col)| col).min().unwrap();
+    placement
+      .cells()
+      .iter()
+      .map(|&(row, col)| (row - top, col - left))
+      .sorted()
+      .collect()
+  }
+
+  #[gtest]
+  fn test_restricted_orientations() {
+    let board = PolyominoBoard::rectangle(4, 4);
+    let pieces =
+      ['A', 'B', 'C', 'D'].map(|label| Polyomino::new(label, [(0, 0), (0, 1), (0, 2), (1, 0)]));
+    let solutions = PolyominoTiling::new(board.clone())
+      .with_pieces(pieces.clone())
+      .dlx()
+      .find_solutions()
+      .with_names()
+      .collect_vec();
+    expect_that!(solutions, not(empty()));
+
+    // Restricting the first piece's orientations keeps the solutions with it
+    // in one of the remaining orientations.
+    for orientations in [Orientations::Fixed, Orientations::Rotate] {
+      let mut pieces = pieces.clone();
+      pieces[0] = pieces[0].clone().with_orientations(orientations);
+      let shapes = pieces[0].oriented_shapes();
+      let expected = solutions
+        .iter()
+        .filter(|solution| {
+          solution
+            .iter()
+            .any(|placement| placement.piece() == 0 && shapes.contains(&placement_shape(placement)))
+        })
+        .count();
+      expect_that!(expected, lt(solutions.len()));
+      let tiling = PolyominoTiling::new(board.clone()).with_pieces(pieces);
+      expect_eq!(tiling.dlx().count_solutions(), expected as u64);
+    }
+  }
+
+  #[gtest]
+  fn test_pentominoes_3x20() {
+    let tiling =
+      PolyominoTiling::new(PolyominoBoard::rectangle(3, 20)).with_pieces(Polyomino::pentominoes());
+    let solutions = tiling.dlx().find_solutions().with_names().collect_vec();
+    // The 2 tilings, each turned around and flipped in the 4 ways the board
+    // allows.
+    expect_eq!(solutions.len(), 8);
+    let boards = solutions
+      .iter()
+      .map(|solution| tiling.render(solution))
+      .collect::<BTreeSet<_>>();
+    expect_eq!(boards.len(), 8);
+    expect_that!(
+      boards,
+      contains(eq(concat!(
+        "UUXIIIIINNNFTWYYYYZV\n",
+        "UXXXPPLNNFFFTWWYZZZV\n",
+        "UUXPPPLLLLFTTTWWZVVV\n"
+      )))
+    );
+  }
+}
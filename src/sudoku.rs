@@ -0,0 +1,991 @@
+use std::{
+  collections::{BTreeSet, HashMap},
+  error::Error,
+  fmt::{self, Display, Formatter},
+  str::FromStr,
+};
+
+use rand::Rng;
+
+use crate::{
+  ColorItem, Constraint, Dlx, DlxIteratorWithNames, DlxWithGivens, GivensError, HeaderType,
+};
+
+/// An item of the exact cover problem built by `Sudoku::dlx`. Rows, columns,
+/// boxes and values are numbered from 0, except that values are numbered
+/// from 1 as they appear in the grid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SudokuItem {
+  /// The cell at `(row, col)` has a value.
+  Cell { row: usize, col: usize },
+  /// Row `row` contains `value`.
+  Row { row: usize, value: u32 },
+  /// Column `col` contains `value`.
+  Column { col: usize, value: u32 },
+  /// Box `index`, counting across then down, contains `value`.
+  Box { index: usize, value: u32 },
+  /// Diagonal `index` contains `value`, where 0 is the diagonal from the top
+  /// left and 1 the diagonal from the top right.
+  Diagonal { index: usize, value: u32 },
+  /// Region `index`, in the order they were added to the `Sudoku`, contains
+  /// `value`.
+  Region { index: usize, value: u32 },
+  /// A secondary item keeping the cells `first` and `second` from both
+  /// holding `value`.
+  Apart {
+    first: (usize, usize),
+    second: (usize, usize),
+    value: u32,
+  },
+  /// A secondary item keeping the cells `first` and `second` from holding
+  /// `low` and `low + 1`, in either order.
+  NonConsecutive {
+    first: (usize, usize),
+    second: (usize, usize),
+    low: u32,
+  },
+}
+
+/// A rule which forbids some pairs of values in two related cells.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum PairRule {
+  /// The cells can't hold the same value.
+  Apart,
+  /// The cells can't hold consecutive values.
+  NonConsecutive,
+}
+
+/// Two cells related by a `PairRule`, as `(row, col)` positions.
+type CellPair = (PairRule, (usize, usize), (usize, usize));
+
+/// The subsets of the exact cover problem built by `Sudoku::dlx`: placing
+/// `value` in the cell at `(row, col)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SudokuChoice {
+  row: usize,
+  col: usize,
+  value: u32,
+}
+
+impl SudokuChoice {
+  pub fn new(row: usize, col: usize, value: u32) -> Self {
+    Self { row, col, value }
+  }
+
+  pub fn row(&self) -> usize {
+    self.row
+  }
+
+  pub fn col(&self) -> usize {
+    self.col
+  }
+
+  pub fn value(&self) -> u32 {
+    self.value
+  }
+}
+
+/// A Sudoku grid of `size × size` cells split into `box_size × box_size`
+/// boxes, where `size = box_size²`, with each cell either empty or holding a
+/// value in `1..=size`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SudokuGrid {
+  box_size: usize,
+  cells: Vec<Option<u32>>,
+}
+
+impl SudokuGrid {
+  /// Creates an empty grid. Panics if `box_size` is 0.
+  pub fn new(box_size: usize) -> Self {
+    assert!(box_size > 0, "Sudoku box size must be positive");
+    let size = box_size * box_size;
+    Self {
+      box_size,
+      cells: vec![None; size * size],
+    }
+  }
+
+  pub fn box_size(&self) -> usize {
+    self.box_size
+  }
+
+  /// The number of rows, columns and boxes, which is also the largest value.
+  pub fn size(&self) -> usize {
+    self.box_size * self.box_size
+  }
+
+  fn index(&self, row: usize, col: usize) -> usize {
+    let size = self.size();
+    assert!(
+      row < size && col < size,
+      "Cell ({row}, {col}) is outside of a {size}x{size} grid"
+    );
+    row * size + col
+  }
+
+  pub fn get(&self, row: usize, col: usize) -> Option<u32> {
+    self.cells[self.index(row, col)]
+  }
+
+  /// Sets the value of the cell at `(row, col)`, or clears it if `value` is
+  /// None. Panics if the cell or value is out of range.
+  pub fn set(&mut self, row: usize, col: usize, value: Option<u32>) {
+    if let Some(value) = value {
+      assert!(
+        (1..=self.size() as u32).contains(&value),
+        "Value {value} is out of range for a {0}x{0} grid",
+        self.size()
+      );
+    }
+    let idx = self.index(row, col);
+    self.cells[idx] = value;
+  }
+
+  /// Returns true if every cell has a value.
+  pub fn is_complete(&self) -> bool {
+    self.cells.iter().all(Option::is_some)
+  }
+
+  /// Iterates over the filled cells, as the choices which would fill them.
+  pub fn choices(&self) -> impl Iterator<Item = SudokuChoice> + '_ {
+    let size = self.size();
+    self
+      .cells
+      .iter()
+      .enumerate()
+      .filter_map(move |(idx, value)| Some(SudokuChoice::new(idx / size, idx % size, (*value)?)))
+  }
+
+  /// Fills in the cells named by `choices`, such as a solution found with
+  /// `with_names` on the problem built by `Sudoku::dlx`.
+  pub fn apply<'a>(&mut self, choices: impl IntoIterator<Item = &'a SudokuChoice>) {
+    for choice in choices {
+      self.set(choice.row, choice.col, Some(choice.value));
+    }
+  }
+}
+
+/// The ways a Sudoku grid read by `SudokuGrid::from_str` can be malformed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SudokuParseError {
+  /// The number of cells isn't the fourth power of a box size.
+  InvalidCellCount(usize),
+  /// A character isn't a value, an empty cell or a separator.
+  InvalidCharacter(char),
+  /// A value is too large for the size of the grid.
+  ValueOutOfRange(char),
+  /// A value written as a number is too large for the size of the grid.
+  NumberOutOfRange(u32),
+}
+
+impl Display for SudokuParseError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      SudokuParseError::InvalidCellCount(count) => {
+        write!(f, "{count} cells don't make a Sudoku grid")
+      }
+      SudokuParseError::InvalidCharacter(c) => write!(f, "Invalid character {c:?}"),
+      SudokuParseError::ValueOutOfRange(c) => write!(f, "Value {c:?} is out of range"),
+      SudokuParseError::NumberOutOfRange(value) => write!(f, "Value {value} is out of range"),
+    }
+  }
+}
+
+impl Error for SudokuParseError {}
+
+/// Returns the box size of a grid with `num_cells` cells, if there is one.
+fn box_size_for(num_cells: usize) -> Option<usize> {
+  (1..)
+    .find(|&box_size: &usize| box_size.pow(4) >= num_cells)
+    .filter(|box_size| box_size.pow(4) == num_cells)
+}
+
+impl SudokuGrid {
+  /// Reads the cells of `s` as whitespace-separated numbers, as written by
+  /// `Display` for grids too large for one character per cell. Returns None
+  /// if `s` isn't in that format: if no cell takes more than one character,
+  /// the grid reads the same either way, and if a cell takes more characters
+  /// than the largest value, it must be a row of one character per cell.
+  fn from_numbers(s: &str) -> Option<Result<Self, SudokuParseError>> {
+    let tokens = s
+      .split(|c: char| c.is_whitespace() || matches!(c, '|' | '-' | '+'))
+      .filter(|token| !token.is_empty())
+      .collect::<Vec<_>>();
+    let box_size = box_size_for(tokens.len())?;
+    let size = (box_size * box_size) as u32;
+    let max_len = size.to_string().len();
+    let is_number = |token: &&str| {
+      *token == "." || (token.len() <= max_len && token.bytes().all(|b| b.is_ascii_digit()))
+    };
+    if !tokens.iter().any(|token| token.len() > 1) || !tokens.iter().all(is_number) {
+      return None;
+    }
+
+    let cells = tokens
+      .into_iter()
+      .map(|token| match token {
+        "." => Ok(None),
+        _ => match token.parse().unwrap() {
+          0 => Ok(None),
+          value if value > size => Err(SudokuParseError::NumberOutOfRange(value)),
+          value => Ok(Some(value)),
+        },
+      })
+      .collect::<Result<_, _>>();
+    Some(cells.map(|cells| Self { box_size, cells }))
+  }
+}
+
+impl FromStr for SudokuGrid {
+  type Err = SudokuParseError;
+
+  /// Reads a grid with one character per cell, row by row. Values are written
+  /// as digits and then letters (`A` is 10, up to `Z` for 35), and empty cells
+  /// as `.` or `0`. Whitespace and the box separators `|`, `-` and `+` are
+  /// ignored. The box size is inferred from the number of cells.
+  ///
+  /// Grids too large for one character per cell are read as numbers
+  /// separated by whitespace, with `.` or `0` for empty cells, which is how
+  /// `Display` writes them.
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    if let Some(grid) = Self::from_numbers(s) {
+      return grid;
+    }
+
+    let values = s
+      .chars()
+      .filter(|c| !c.is_whitespace() && !matches!(c, '|' | '-' | '+'))
+      .map(|c| match c {
+        '.' | '0' => Ok((c, None)),
+        _ => c
+          .to_digit(36)
+          .map(|value| (c, Some(value)))
+          .ok_or(SudokuParseError::InvalidCharacter(c)),
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    let box_size =
+      box_size_for(values.len()).ok_or(SudokuParseError::InvalidCellCount(values.len()))?;
+    let size = (box_size * box_size) as u32;
+    let cells = values
+      .into_iter()
+      .map(|(c, value)| match value {
+        Some(value) if value > size => Err(SudokuParseError::ValueOutOfRange(c)),
+        _ => Ok(value),
+      })
+      .collect::<Result<_, _>>()?;
+    Ok(Self { box_size, cells })
+  }
+}
+
+impl Display for SudokuGrid {
+  /// Writes one row per line in the format read by `from_str`: one character
+  /// per cell, or for grids with values too large for one character,
+  /// space-separated numbers.
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    let size = self.size();
+    for row in self.cells.chunks(size) {
+      if size <= 35 {
+        for value in row {
+          let c = match value {
+            Some(value) => char::from_digit(*value, 36).unwrap().to_ascii_uppercase(),
+            None => '.',
+          };
+          write!(f, "{c}")?;
+        }
+      } else {
+        let tokens = row
+          .iter()
+          .map(|value| match value {
+            Some(value) => value.to_string(),
+            None => ".".to_owned(),
+          })
+          .collect::<Vec<_>>();
+        write!(f, "{}", tokens.join(" "))?;
+      }
+      writeln!(f)?;
+    }
+    Ok(())
+  }
+}
+
+/// The rules of Sudoku for grids with boxes of a given size, solved as an
+/// exact cover problem: every cell gets one value, and every row, column and
+/// box gets each value once.
+///
+/// Variants add rules with the `with_*` methods. Regions which must contain
+/// every value once (diagonals, windows, jigsaw pieces) become more primary
+/// items, while rules which forbid pairs of values in related cells become
+/// secondary items, which the two cells give different colors. For example,
+/// an anti-knight Windoku is `Sudoku::new(3).with_windows().with_anti_knight()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sudoku {
+  box_size: usize,
+  /// False for jigsaw Sudoku, whose regions replace the boxes.
+  boxes: bool,
+  diagonals: bool,
+  regions: Vec<Vec<(usize, usize)>>,
+  /// The offsets from a cell to the later cells it is related to by each
+  /// pair rule.
+  pair_rules: Vec<(PairRule, Vec<(usize, isize)>)>,
+}
+
+impl Sudoku {
+  /// The rules for grids of `box_size² × box_size²` cells. Panics if
+  /// `box_size` is 0.
+  pub fn new(box_size: usize) -> Self {
+    assert!(box_size > 0, "Sudoku box size must be positive");
+    Self {
+      box_size,
+      boxes: true,
+      diagonals: false,
+      regions: Vec::new(),
+      pair_rules: Vec::new(),
+    }
+  }
+
+  pub fn box_size(&self) -> usize {
+    self.box_size
+  }
+
+  pub fn size(&self) -> usize {
+    self.box_size * self.box_size
+  }
+
+  /// Requires both main diagonals to contain every value once (X-Sudoku).
+  pub fn with_diagonals(mut self) -> Self {
+    self.diagonals = true;
+    self
+  }
+
+  /// Requires `cells` to contain every value once. Panics unless `cells` are
+  /// `size` distinct cells of the grid.
+  pub fn with_region(mut self, cells: impl IntoIterator<Item = (usize, usize)>) -> Self {
+    let size = self.size();
+    let cells = cells.into_iter().collect::<Vec<_>>();
+    assert!(
+      cells.iter().all(|&(row, col)| row < size && col < size),
+      "Region {cells:?} has cells outside of a {size}x{size} grid"
+    );
+    assert_eq!(
+      cells.iter().collect::<BTreeSet<_>>().len(),
+      size,
+      "Region {cells:?} doesn't have {size} distinct cells"
+    );
+    self.regions.push(cells);
+    self
+  }
+
+  /// Adds the windows of Windoku: the boxes offset by one cell from the top
+  /// left of each box, and separated by one cell, which must also contain
+  /// every value once.
+  pub fn with_windows(self) -> Self {
+    let box_size = self.box_size;
+    let corners = (0..box_size.saturating_sub(1)).map(|i| 1 + i * (box_size + 1));
+    corners
+      .clone()
+      .flat_map(|row| corners.clone().map(move |col| (row, col)))
+      .fold(self, |sudoku, (top, left)| {
+        sudoku.with_region(
+          (top..(top + box_size))
+            .flat_map(|row| (left..(left + box_size)).map(move |col| (row, col))),
+        )
+      })
+  }
+
+  /// Replaces the boxes with irregular regions (jigsaw Sudoku). Panics unless
+  /// the regions split the grid into `size` regions of `size` cells.
+  pub fn with_jigsaw<R>(mut self, regions: impl IntoIterator<Item = R>) -> Self
+  where
+    R: IntoIterator<Item = (usize, usize)>,
+  {
+    let size = self.size();
+    let first_region = self.regions.len();
+    for region in regions {
+      self = self.with_region(region);
+    }
+    let cells = self.regions[first_region..]
+      .iter()
+      .flatten()
+      .collect::<BTreeSet<_>>();
+    assert!(
+      self.regions.len() - first_region == size && cells.len() == size * size,
+      "Jigsaw regions don't split the grid into {size} regions"
+    );
+    self.boxes = false;
+    self
+  }
+
+  /// Like `with_jigsaw`, with the regions given as a grid of characters in the
+  /// format read by `SudokuGrid::from_str`, where cells with the same
+  /// character are in the same region.
+  pub fn with_jigsaw_layout(self, layout: &str) -> Self {
+    let size = self.size();
+    let mut regions = HashMap::<char, Vec<(usize, usize)>>::new();
+    let mut order = Vec::new();
+    for (idx, c) in layout.chars().filter(|c| !c.is_whitespace()).enumerate() {
+      regions
+        .entry(c)
+        .or_insert_with(|| {
+          order.push(c);
+          Vec::new()
+        })
+        .push((idx / size, idx % size));
+    }
+    let regions = order
+      .into_iter()
+      .map(|c| regions.remove(&c).unwrap())
+      .collect::<Vec<_>>();
+    self.with_jigsaw(regions)
+  }
+
+  fn with_pair_rule(mut self, rule: PairRule, offsets: &[(usize, isize)]) -> Self {
+    self.pair_rules.push((rule, offsets.to_vec()));
+    self
+  }
+
+  /// Forbids the same value in cells a knight's move apart.
+  pub fn with_anti_knight(self) -> Self {
+    self.with_pair_rule(PairRule::Apart, &[(1, -2), (1, 2), (2, -1), (2, 1)])
+  }
+
+  /// Forbids the same value in diagonally adjacent cells. Orthogonally
+  /// adjacent cells already share a row or column.
+  pub fn with_anti_king(self) -> Self {
+    self.with_pair_rule(PairRule::Apart, &[(1, -1), (1, 1)])
+  }
+
+  /// Forbids consecutive values in orthogonally adjacent cells.
+  pub fn with_non_consecutive(self) -> Self {
+    self.with_pair_rule(PairRule::NonConsecutive, &[(0, 1), (1, 0)])
+  }
+
+  /// The primary items covered by placing `value` at `(row, col)`.
+  fn primary_items_for(&self, row: usize, col: usize, value: u32) -> Vec<SudokuItem> {
+    let box_size = self.box_size;
+    let mut items = vec![
+      SudokuItem::Cell { row, col },
+      SudokuItem::Row { row, value },
+      SudokuItem::Column { col, value },
+    ];
+    if self.boxes {
+      let index = (row / box_size) * box_size + col / box_size;
+      items.push(SudokuItem::Box { index, value });
+    }
+    if self.diagonals {
+      if row == col {
+        items.push(SudokuItem::Diagonal { index: 0, value });
+      }
+      if row + col == self.size() - 1 {
+        items.push(SudokuItem::Diagonal { index: 1, value });
+      }
+    }
+    for (index, region) in self.regions.iter().enumerate() {
+      if region.contains(&(row, col)) {
+        items.push(SudokuItem::Region { index, value });
+      }
+    }
+    items
+  }
+
+  /// The pairs of cells related by the pair rules, with `first` before
+  /// `second` in reading order.
+  fn related_pairs(&self) -> BTreeSet<CellPair> {
+    let size = self.size() as isize;
+    let mut pairs = BTreeSet::new();
+    for (rule, offsets) in &self.pair_rules {
+      for row in 0..size {
+        for col in 0..size {
+          for &(d_row, d_col) in offsets {
+            let (other_row, other_col) = (row + d_row as isize, col + d_col);
+            if other_row < size && (0..size).contains(&other_col) {
+              let first = (row as usize, col as usize);
+              let second = (other_row as usize, other_col as usize);
+              pairs.insert((*rule, first, second));
+            }
+          }
+        }
+      }
+    }
+    pairs
+  }
+
+  /// Builds the exact cover problem for an empty grid, with a subset for each
+  /// value of each cell. Clues are applied as givens (see
+  /// `Dlx::with_givens`), which is what `solve` and the other methods here
+  /// do.
+  pub fn dlx(&self) -> Dlx<SudokuItem, SudokuChoice> {
+    let size = self.size();
+    let values = 1..=size as u32;
+
+    // The secondary constraints of each choice, keyed by cell and value. The
+    // choices of a pair's first cell give its items color 0 and those of the
+    // second cell color 1, so only the forbidden combinations conflict.
+    let mut secondary = HashMap::<((usize, usize), u32), Vec<Constraint<SudokuItem>>>::new();
+    let mut secondary_items = Vec::new();
+    for (rule, first, second) in self.related_pairs() {
+      for value in values.clone() {
+        let (item, forbidden) = match rule {
+          PairRule::Apart => (
+            SudokuItem::Apart {
+              first,
+              second,
+              value,
+            },
+            vec![value],
+          ),
+          PairRule::NonConsecutive if value < size as u32 => (
+            SudokuItem::NonConsecutive {
+              first,
+              second,
+              low: value,
+            },
+            vec![value, value + 1],
+          ),
+          PairRule::NonConsecutive => continue,
+        };
+        secondary_items.push(item);
+        for (color, cell) in [first, second].into_iter().enumerate() {
+          for &value in &forbidden {
+            secondary
+              .entry((cell, value))
+              .or_default()
+              .push(ColorItem::new(item, color as u32).into());
+          }
+        }
+      }
+    }
+
+    let cells = (0..size).flat_map(|row| (0..size).map(move |col| (row, col)));
+    let choices = cells
+      .flat_map(|(row, col)| {
+        values
+          .clone()
+          .map(move |value| SudokuChoice::new(row, col, value))
+      })
+      .collect::<Vec<_>>();
+    let mut primary_items = Vec::new();
+    let subsets = choices
+      .into_iter()
+      .map(|choice| {
+        let (row, col, value) = (choice.row, choice.col, choice.value);
+        let primary = self.primary_items_for(row, col, value);
+        primary_items.extend(primary.iter().copied());
+        let constraints = primary
+          .into_iter()
+          .map(Constraint::Primary)
+          .chain(secondary.remove(&((row, col), value)).unwrap_or_default())
+          .collect::<Vec<_>>();
+        (choice, constraints)
+      })
+      .collect::<Vec<_>>();
+
+    // Every primary item is covered by some choice, so collecting them from
+    // the choices lists each of them.
+    let items = primary_items
+      .into_iter()
+      .collect::<BTreeSet<_>>()
+      .into_iter()
+      .map(|item| (item, HeaderType::Primary))
+      .chain(
+        secondary_items
+          .into_iter()
+          .map(|item| (item, HeaderType::Secondary)),
+      );
+    Dlx::new(items, subsets)
+  }
+
+  /// Calls `f` with the problem for `clues` and its givens applied, or
+  /// returns `default` if the clues break the rules.
+  fn with_clues<T>(
+    &self,
+    clues: &SudokuGrid,
+    default: T,
+    f: impl FnOnce(&mut DlxWithGivens<'_, SudokuItem, SudokuChoice>) -> T,
+  ) -> T {
+    assert_eq!(
+      clues.box_size, self.box_size,
+      "The grid's box size doesn't match the rules"
+    );
+    let mut dlx = self.dlx();
+    let givens = clues.choices().collect::<Vec<_>>();
+    let result = match dlx.with_givens(&givens) {
+      Ok(mut with_givens) => f(&mut with_givens),
+      Err(GivensError::ConflictingGiven(_)) => default,
+      Err(GivensError::UnknownSubset(choice)) => {
+        unreachable!("Every choice is a subset, but {choice:?} wasn't found")
+      }
+    };
+    result
+  }
+
+  /// Finds up to `limit` ways to fill in the empty cells of `clues`, as
+  /// completed grids.
+  pub fn solutions_up_to(&self, clues: &SudokuGrid, limit: usize) -> Vec<SudokuGrid> {
+    self.with_clues(clues, Vec::new(), |dlx| {
+      dlx
+        .find_solutions()
+        .with_names()
+        .take(limit)
+        .map(|choices| {
+          let mut grid = clues.clone();
+          grid.apply(&choices);
+          grid
+        })
+        .collect()
+    })
+  }
+
+  /// Fills in the empty cells of `clues`, or returns None if the puzzle has
+  /// no solution. If there are several solutions, returns the first found.
+  pub fn solve(&self, clues: &SudokuGrid) -> Option<SudokuGrid> {
+    self.solutions_up_to(clues, 1).pop()
+  }
+
+  /// Counts the ways to fill in `clues`, stopping once `limit` are found.
+  pub fn count_solutions_up_to(&self, clues: &SudokuGrid, limit: u64) -> u64 {
+    self.with_clues(clues, 0, |dlx| dlx.count_solutions_up_to(limit))
+  }
+
+  /// Returns true if `clues` can be filled in exactly one way, as a proper
+  /// puzzle should.
+  pub fn has_unique_solution(&self, clues: &SudokuGrid) -> bool {
+    self.count_solutions_up_to(clues, 2) == 1
+  }
+
+  /// Generates a puzzle with a unique solution, from which no clue can be
+  /// removed without losing uniqueness (see `Dlx::generate_puzzle`). Returns
+  /// None if no grid follows the rules.
+  pub fn generate<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<SudokuGrid> {
+    let givens = self.dlx().generate_puzzle(rng)?;
+    let mut clues = SudokuGrid::new(self.box_size);
+    clues.apply(&givens);
+    Some(clues)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use googletest::prelude::*;
+  use itertools::Itertools;
+  use rand::{rngs::StdRng, SeedableRng};
+
+  use crate::{HeaderType, Sudoku, SudokuGrid, SudokuItem, SudokuParseError};
+
+  const PUZZLE: &str = "
+    53..7....
+    6..195...
+    .98....6.
+    8...6...3
+    4..8.3..1
+    7...2...6
+    .6....28.
+    ...419..5
+    ....8..79
+  ";
+
+  const SOLUTION: &str = "\
+534678912
+672195348
+198342567
+859761423
+426853791
+713924856
+961537284
+287419635
+345286179
+";
+
+  #[gtest]
+  fn test_solve() {
+    let clues = PUZZLE.parse::<SudokuGrid>().unwrap();
+    let sudoku = Sudoku::new(3);
+
+    let solution = sudoku.solve(&clues).unwrap();
+    expect_true!(solution.is_complete());
+    expect_eq!(solution.to_string(), SOLUTION);
+    expect_true!(sudoku.has_unique_solution(&clues));
+  }
+
+  #[gtest]
+  fn test_dlx_size() {
+    let dlx = Sudoku::new(3).dlx();
+    expect_eq!(dlx.items().count(), 324);
+    expect_eq!(dlx.subsets().count(), 729);
+    expect_true!(dlx.subsets().all(|(_, constraints)| constraints.len() == 4));
+    expect_that!(
+      dlx.items().next(),
+      some(eq((
+        &SudokuItem::Cell { row: 0, col: 0 },
+        HeaderType::Primary
+      )))
+    );
+  }
+
+  #[gtest]
+  fn test_multiple_and_no_solutions() {
+    let sudoku = Sudoku::new(2);
+    // Every 4x4 grid with a full first row has several completions.
+    let clues = "1234 .... .... ....".parse::<SudokuGrid>().unwrap();
+    expect_eq!(sudoku.count_solutions_up_to(&clues, u64::MAX), 12);
+    expect_false!(sudoku.has_unique_solution(&clues));
+    let solutions = sudoku.solutions_up_to(&clues, 5);
+    expect_eq!(solutions.len(), 5);
+    expect_true!(solutions.iter().all_unique());
+
+    // Two 1s in the first row.
+    let clues = "11.. .... .... ....".parse::<SudokuGrid>().unwrap();
+    expect_eq!(sudoku.count_solutions_up_to(&clues, u64::MAX), 0);
+    expect_eq!(sudoku.solve(&clues), None);
+
+    // Not a contradiction of the clues alone, but nothing can go in (1, 0).
+    let clues = "1... .234 .... ....".parse::<SudokuGrid>().unwrap();
+    expect_eq!(sudoku.solve(&clues), None);
+  }
+
+  #[gtest]
+  fn test_generate() {
+    let sudoku = Sudoku::new(3);
+    let mut rng = StdRng::seed_from_u64(1);
+    let clues = sudoku.generate(&mut rng).unwrap();
+    expect_true!(sudoku.has_unique_solution(&clues));
+    for (row, col) in cells(&clues).filter(|&(row, col)| clues.get(row, col).is_some()) {
+      let mut fewer = clues.clone();
+      fewer.set(row, col, None);
+      expect_false!(sudoku.has_unique_solution(&fewer));
+    }
+
+    // No 4x4 grid follows the anti-king rule.
+    expect_eq!(Sudoku::new(2).with_anti_king().generate(&mut rng), None);
+  }
+
+  #[gtest]
+  fn test_parse_and_print() {
+    let grid = "12|..\n..|..\n--+--\n..|..\n..|.4\n"
+      .parse::<SudokuGrid>()
+      .unwrap();
+    expect_eq!(grid.box_size(), 2);
+    expect_eq!(grid.get(0, 1), Some(2));
+    expect_eq!(grid.get(3, 3), Some(4));
+    expect_eq!(grid.get(1, 1), None);
+    expect_eq!(grid.to_string(), "12..\n....\n....\n...4\n");
+
+    let big = Sudoku::new(4).solve(&SudokuGrid::new(4)).unwrap();
+    expect_eq!(big.to_string().parse::<SudokuGrid>(), Ok(big.clone()));
+    expect_true!(big.to_string().contains('G'));
+
+    // Grids with values above 35 are written as numbers.
+    let mut huge = SudokuGrid::new(6);
+    for (row, col) in (0..36).flat_map(|row| (0..36).map(move |col| (row, col))) {
+      if (row + col) % 5 != 0 {
+        let value = (row * 6 + row / 6 + col) % 36 + 1;
+        huge.set(row, col, Some(value as u32));
+      }
+    }
+    let text = huge.to_string();
+    expect_true!(text.starts_with(". 2 3 4 5 . 7 8 9 10 . 12"));
+    expect_eq!(text.parse::<SudokuGrid>(), Ok(huge));
+    expect_eq!(
+      text.replacen("10", "37", 1).parse::<SudokuGrid>(),
+      Err(SudokuParseError::NumberOutOfRange(37))
+    );
+
+    expect_eq!(
+      "123".parse::<SudokuGrid>(),
+      Err(SudokuParseError::InvalidCellCount(3))
+    );
+    expect_eq!(
+      "12?. .... .... ....".parse::<SudokuGrid>(),
+      Err(SudokuParseError::InvalidCharacter('?'))
+    );
+    expect_eq!(
+      "125. .... .... ....".parse::<SudokuGrid>(),
+      Err(SudokuParseError::ValueOutOfRange('5'))
+    );
+  }
+
+  /// The rules of 4x4 Latin squares, which are Sudoku without the boxes.
+  fn latin_squares() -> Sudoku {
+    Sudoku::new(2).with_jigsaw((0..4).map(|row| (0..4).map(move |col| (row, col))))
+  }
+
+  fn cells(grid: &SudokuGrid) -> impl Iterator<Item = (usize, usize)> {
+    let size = grid.size();
+    (0..size).flat_map(move |row| (0..size).map(move |col| (row, col)))
+  }
+
+  /// Returns true if no two cells `offset` apart in `grid` hold values for
+  /// which `forbidden` is true.
+  fn pairs_allowed(
+    grid: &SudokuGrid,
+    offsets: &[(isize, isize)],
+    forbidden: impl Fn(u32, u32) -> bool,
+  ) -> bool {
+    let size = grid.size() as isize;
+    cells(grid).all(|(row, col)| {
+      offsets.iter().all(|&(d_row, d_col)| {
+        let (other_row, other_col) = (row as isize + d_row, col as isize + d_col);
+        !(0..size).contains(&other_row)
+          || !(0..size).contains(&other_col)
+          || !forbidden(
+            grid.get(row, col).unwrap(),
+            grid.get(other_row as usize, other_col as usize).unwrap(),
+          )
+      })
+    })
+  }
+
+  fn all_distinct(grid: &SudokuGrid, cells: impl IntoIterator<Item = (usize, usize)>) -> bool {
+    cells
+      .into_iter()
+      .map(|(row, col)| grid.get(row, col))
+      .all_unique()
+  }
+
+  /// Checks that `sudoku` finds exactly the completions of `clues` under
+  /// the rules of `base` for which `allowed` is true. Returns how many of
+  /// `base`'s solutions are not allowed.
+  fn expect_variant(
+    sudoku: Sudoku,
+    base: Sudoku,
+    clues: &SudokuGrid,
+    allowed: impl Fn(&SudokuGrid) -> bool,
+  ) -> usize {
+    let solutions = base.solutions_up_to(clues, usize::MAX);
+    let num_solutions = solutions.len();
+    let expected = solutions
+      .into_iter()
+      .filter(|grid| allowed(grid))
+      .sorted_by_key(|grid| grid.to_string())
+      .collect_vec();
+    expect_that!(expected, not(empty()));
+    let actual = sudoku
+      .solutions_up_to(clues, usize::MAX)
+      .into_iter()
+      .sorted_by_key(|grid| grid.to_string())
+      .collect_vec();
+    expect_eq!(actual, expected);
+    num_solutions - expected.len()
+  }
+
+  const KNIGHT: [(isize, isize); 8] = [
+    (-2, -1),
+    (-2, 1),
+    (-1, -2),
+    (-1, 2),
+    (1, -2),
+    (1, 2),
+    (2, -1),
+    (2, 1),
+  ];
+  const DIAGONAL: [(isize, isize); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+  const ORTHOGONAL: [(isize, isize); 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+
+  #[gtest]
+  fn test_region_variants() {
+    let empty = SudokuGrid::new(2);
+    expect_eq!(Sudoku::new(2).count_solutions_up_to(&empty, u64::MAX), 288);
+    expect_eq!(latin_squares().count_solutions_up_to(&empty, u64::MAX), 576);
+
+    let diagonals = |grid: &SudokuGrid| {
+      all_distinct(grid, (0..4).map(|i| (i, i))) && all_distinct(grid, (0..4).map(|i| (i, 3 - i)))
+    };
+    expect_eq!(
+      expect_variant(
+        Sudoku::new(2).with_diagonals(),
+        Sudoku::new(2),
+        &empty,
+        diagonals
+      ),
+      240
+    );
+    expect_variant(
+      Sudoku::new(2).with_windows(),
+      Sudoku::new(2),
+      &empty,
+      |grid| all_distinct(grid, [(1, 1), (1, 2), (2, 1), (2, 2)]),
+    );
+
+    let layout = "
+      AAAB
+      CADB
+      CCDB
+      CDDB
+    ";
+    expect_variant(
+      Sudoku::new(2).with_jigsaw_layout(layout),
+      latin_squares(),
+      &empty,
+      |grid| {
+        ('A'..='D').all(|region| {
+          all_distinct(
+            grid,
+            cells(grid).filter(|&(row, col)| {
+              layout.split_whitespace().nth(row).unwrap().chars().nth(col) == Some(region)
+            }),
+          )
+        })
+      },
+    );
+  }
+
+  #[gtest]
+  fn test_pair_variants() {
+    let empty = SudokuGrid::new(2);
+    expect_variant(
+      Sudoku::new(2).with_anti_knight(),
+      Sudoku::new(2),
+      &empty,
+      |grid| pairs_allowed(grid, &KNIGHT, |a, b| a == b),
+    );
+
+    // Anti-king and non-consecutive grids don't exist at 4x4, so check the
+    // completions of 9x9 grids with the middle band cleared instead.
+    let anti_king = "
+      123456789
+      879123456
+      456897123
+      217364598
+      568219374
+      394578612
+      681935247
+      932741865
+      745682931
+    ";
+    let non_consecutive = "
+      163759248
+      427183695
+      859426137
+      571948362
+      936275814
+      284631579
+      642817953
+      318594726
+      795362481
+    ";
+    for (sudoku, grid, offsets, forbidden) in [
+      (
+        Sudoku::new(3).with_anti_king(),
+        anti_king,
+        &DIAGONAL,
+        (|a, b| a == b) as fn(u32, u32) -> bool,
+      ),
+      (
+        Sudoku::new(3).with_non_consecutive(),
+        non_consecutive,
+        &ORTHOGONAL,
+        |a: u32, b: u32| a.abs_diff(b) == 1,
+      ),
+    ] {
+      let mut clues = grid.parse::<SudokuGrid>().unwrap();
+      expect_true!(pairs_allowed(&clues, offsets, forbidden));
+      for (row, col) in cells(&clues.clone()).filter(|&(row, _)| (3..6).contains(&row)) {
+        clues.set(row, col, None);
+      }
+      let rejected = expect_variant(sudoku, Sudoku::new(3), &clues, |grid| {
+        pairs_allowed(grid, offsets, forbidden)
+      });
+      expect_that!(rejected, gt(0));
+    }
+  }
+}